@@ -6,8 +6,8 @@
 #![no_main]
 #![feature(asm)]
 
+use arch::mpu::{AccessPermission, MemoryType, Region};
 use arch::nvic::Nvic;
-use arch::systick::Systick;
 use core::fmt::Write as CoreWrite;
 use core::mem::MaybeUninit;
 use cortex_m_semihosting::hio::hstdout;
@@ -19,14 +19,18 @@ use stm32f429zi::rcc::RCC;
 use stm32f429zi::serial::Serial;
 use stm32f429zi::syscfg::Syscfg;
 use embedded_hal::serial::{Read, Write};
+use kernel::clock::Clock;
 use kernel::{interrupt_manager::InterruptManager, kernel::SysTick};
 use kernel::kernel::Kernel;
 use kernel::message_manager::MessageManager;
+use kernel::name_registry::{Name, NameRegistry};
 use kernel::process::Process;
+use kernel::process_allocator::ProcessAllocator;
 use kernel::process_list::ProcessListItem;
 use kernel::process_manager::{ProcessId, ProcessManager};
 use kernel::scheduler::simple_scheduler::SimpleScheduler;
 use kernel::scheduler::Scheduler;
+use kernel::timer_manager::{Timer, TimerManager};
 use kernel::{process_create, process_register};
 use log::dhprintln;
 use rt::entry;
@@ -36,16 +40,28 @@ use util::linked_list::ListItem;
 
 entry!(main);
 
-static mut TICK_PROCESS_ID: u32 = 0;
+#[link_section = ".uninit"]
+static mut PROCESS_HEAP: [u8; 16384] = [0; 16384];
 
 pub fn main() -> ! {
     //let mut stdout = hstdout().unwrap();
     //write!(stdout, "Hello, world!").unwrap();
 
     let process = process_create!(app_main, 1024);
-    let tick_process = process_create!(tick, 1024);
+    let mut tick_process = process_create!(tick, 1024);
     let button_process = process_create!(button_callback, 1024);
-    let serial_process = process_create!(serial_func, 1024);
+    let mut serial_process = process_create!(serial_func, 1024);
+    // GPIO and USART are driven from the processes
+    let peripherals = Region::new(
+        0x4000_0000,
+        0x2000_0000,
+        AccessPermission::FullAccess,
+        MemoryType::Device,
+        false,
+    )
+    .unwrap();
+    tick_process.add_region(peripherals);
+    serial_process.add_region(peripherals);
     let registers = RCC.get_registers_ref();
     let syscfg = Syscfg::new(0x4001_3800);
 
@@ -63,11 +79,6 @@ pub fn main() -> ! {
     }
 
     let mut serial = Serial::usart3();
-    let systick = Systick::new();
-    let val = systick.get_ticks_per_10ms();
-    systick.clear_current();
-    systick.set_reload(val * 100);
-    systick.enable();
     let gpioa = Gpio::new(0x4002_0000);
     let gpiob = Gpio::new(0x4002_0400);
     let gpioc = Gpio::new(0x4002_0800);
@@ -101,20 +112,24 @@ pub fn main() -> ! {
     let mut scheduler = SimpleScheduler::new();
     let mut process_manager = ProcessManager::new();
     process_register!(scheduler, process_manager, process);
-    process_register!(scheduler, process_manager, tick_process, tick_process_id);
+    process_register!(scheduler, process_manager, tick_process);
     process_register!(scheduler, process_manager, serial_process);
     process_register!(scheduler, process_manager, button_process);
-    unsafe {
-        TICK_PROCESS_ID = tick_process_id;
-    }
 
-    let mut interrupt_manager = InterruptManager::create(nvic);
+    let mut interrupt_manager = InterruptManager::<2>::create(nvic);
     interrupt_manager.register(IrqId::USART3, serial_loopback);
     interrupt_manager.register(IrqId::EXTI15_10, nothing);
 
     let mut message_buff: [ListItem<u32>; 32] =
         unsafe { core::mem::MaybeUninit::uninit().assume_init() };
-    let message_manager = MessageManager::new(&mut message_buff);
+    let message_manager = MessageManager::new(&mut message_buff, 256);
+    let mut timer_buff: [ListItem<Timer>; 8] =
+        unsafe { core::mem::MaybeUninit::uninit().assume_init() };
+    let timer_manager = TimerManager::new(&mut timer_buff);
+    let process_allocator = ProcessAllocator::new(unsafe { &mut PROCESS_HEAP }, 8);
+    let mut name_buff: [Node<Name, ProcessId>; 8] =
+        unsafe { core::mem::MaybeUninit::uninit().assume_init() };
+    let name_registry = NameRegistry::new(&mut name_buff);
 
     let mut kernel = Kernel::create(
         scheduler,
@@ -122,6 +137,10 @@ pub fn main() -> ! {
         interrupt_manager,
         process_manager,
         message_manager,
+        timer_manager,
+        process_allocator,
+        name_registry,
+        Clock::from_calibration(1),
     );
     unsafe {
         let sp: u32;
@@ -133,7 +152,7 @@ pub fn main() -> ! {
 
 pub unsafe extern "C" fn app_main(_r0: usize, _r1: usize, _r2: usize) -> ! {
     let message: &str = "app_main";
-    print_str(message);
+    let _ = print_str(message);
     loop {
         asm!(
             "mov r0, #5",
@@ -148,8 +167,8 @@ pub unsafe extern "C" fn button_callback() -> ! {
     let message_ptr = message.as_ptr();
     let length = message.bytes().len();
     loop {
-        wait_for_interrupt(IrqId::EXTI15_10);
-        print_str(message);
+        wait_for_interrupt(IrqId::EXTI15_10).unwrap();
+        let _ = print_str(message);
     }
 }
 
@@ -157,15 +176,16 @@ pub unsafe extern "C" fn tick(_r0: usize, _r1: usize, _r2: usize) -> ! {
     let gpiob = Gpio::new(0x4002_0400);
     let mut status = false;
     let mut mode = 0;
+    register_name("tick").unwrap();
     loop {
-        match receive_message() {
-            Some(command) => {
-                mode = command;
-            }
-            None => {}
+        if mode == 0 {
+            mode = wait_message().unwrap();
+            continue;
+        }
+        if let Ok(command) = receive_message() {
+            mode = command;
         }
         if mode == 0 {
-            wait_for_event();
             continue;
         }
         if status {
@@ -174,7 +194,7 @@ pub unsafe extern "C" fn tick(_r0: usize, _r1: usize, _r2: usize) -> ! {
             gpiob.get_registers_ref().bsrr.write(0x1 << 7);
         }
         status = !status;
-        wait_for_systick();
+        wait_for_systick().unwrap();
     }
 }
 
@@ -182,15 +202,16 @@ pub unsafe extern "C" fn serial_func() -> ! {
     let mut serial = Serial::usart3();
     let mut buff = ['\0' as u8; 64];
     let mut pos = 0;
+    let tick_process_id = lookup("tick").unwrap();
     loop {
-        wait_for_interrupt(IrqId::USART3);
+        wait_for_interrupt(IrqId::USART3).unwrap();
         serial.read().map(|c| {
             if c == '\n' {
                 let command = &buff[0..pos];
                 if command == "blink".as_bytes() {
-                    send_message(TICK_PROCESS_ID, 1);
+                    let _ = send_message(tick_process_id, 1);
                 } else if command == "stop".as_bytes() {
-                    send_message(TICK_PROCESS_ID, 0);
+                    let _ = send_message(tick_process_id, 0);
                 }
                 pos = 0;
                 serial.write(c).unwrap();
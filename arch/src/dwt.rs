@@ -0,0 +1,63 @@
+use volatile_register::{RO, RW};
+
+const DEMCR: *mut u32 = 0xE000_EDFC as *mut u32;
+// enables both DWT and ITM
+const DEMCR_TRCENA: u32 = 1 << 24;
+const CTRL_CYCCNTENA: u32 = 1 << 0;
+const CTRL_NOCYCCNT: u32 = 1 << 25;
+
+#[repr(C)]
+pub struct DwtRegisters {
+    pub ctrl: RW<u32>,
+    pub cyccnt: RW<u32>,
+    pub cpicnt: RW<u32>,
+    pub exccnt: RW<u32>,
+    pub sleepcnt: RW<u32>,
+    pub lsucnt: RW<u32>,
+    pub foldcnt: RW<u32>,
+    pub pcsr: RO<u32>,
+}
+
+pub struct Dwt;
+
+impl Dwt {
+    pub const fn new() -> Dwt {
+        Dwt {}
+    }
+
+    pub fn get_registers_ref(&self) -> &DwtRegisters {
+        let registers = 0xE000_1000 as *mut DwtRegisters;
+        unsafe { &*registers }
+    }
+
+    pub fn enable_trace(&self) {
+        unsafe {
+            DEMCR.write_volatile(DEMCR.read_volatile() | DEMCR_TRCENA);
+        }
+    }
+
+    pub fn has_cycle_counter(&self) -> bool {
+        self.get_registers_ref().ctrl.read() & CTRL_NOCYCCNT == 0
+    }
+
+    // the counter runs at the core clock and wraps around
+    pub fn enable_cycle_counter(&self) {
+        self.enable_trace();
+        let registers = self.get_registers_ref();
+        unsafe {
+            registers.cyccnt.write(0);
+            registers.ctrl.modify(|val| val | CTRL_CYCCNTENA);
+        }
+    }
+
+    pub fn disable_cycle_counter(&self) {
+        let registers = self.get_registers_ref();
+        unsafe {
+            registers.ctrl.modify(|val| val & !CTRL_CYCCNTENA);
+        }
+    }
+
+    pub fn cycle_count(&self) -> u32 {
+        self.get_registers_ref().cyccnt.read()
+    }
+}
@@ -0,0 +1,31 @@
+const CPACR: *mut u32 = 0xE000_ED88 as *mut u32;
+const FPCCR: *mut u32 = 0xE000_EF34 as *mut u32;
+// full access to CP10 and CP11
+const CPACR_FPU_ACCESS: u32 = 0xf << 20;
+const FPCCR_ASPEN: u32 = 1 << 31;
+const FPCCR_LSPEN: u32 = 1 << 30;
+const FPCCR_LSPACT: u32 = 1 << 0;
+
+pub struct Fpu;
+
+impl Fpu {
+    pub const fn new() -> Fpu {
+        Fpu {}
+    }
+
+    // exceptions only reserve the space for the FPU context in the frame,
+    // the registers are stacked when the handler uses the FPU first
+    pub fn enable(&self) {
+        unsafe {
+            CPACR.write_volatile(CPACR.read_volatile() | CPACR_FPU_ACCESS);
+            FPCCR.write_volatile(FPCCR.read_volatile() | FPCCR_ASPEN | FPCCR_LSPEN);
+            #[cfg(target_arch = "arm")]
+            asm!("dsb", "isb", options(nomem, nostack));
+        }
+    }
+
+    // an extended frame was reserved but the FPU registers are not stacked yet
+    pub fn is_lazy_stacking_pending(&self) -> bool {
+        unsafe { FPCCR.read_volatile() & FPCCR_LSPACT != 0 }
+    }
+}
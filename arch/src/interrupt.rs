@@ -0,0 +1,94 @@
+// PRIMASK masks every configurable interrupt,
+// BASEPRI only the interrupts with the same or a lower priority
+
+// on other targets, e.g. when testing on the host, there is nothing to mask
+// and the functions below do nothing
+
+pub fn disable() {
+    #[cfg(target_arch = "arm")]
+    unsafe {
+        asm!("cpsid i", options(nomem, nostack));
+    }
+}
+
+/// # Safety
+///
+/// Unmasks every configurable interrupt, so it must not be called inside a critical section
+/// such as `free`. The processor ignores it in unprivileged mode, so it is only meaningful in
+/// privileged mode.
+pub unsafe fn enable() {
+    #[cfg(target_arch = "arm")]
+    asm!("cpsie i", options(nomem, nostack));
+}
+
+pub fn is_disabled() -> bool {
+    #[cfg(target_arch = "arm")]
+    {
+        let primask: u32;
+        unsafe {
+            asm!("mrs {}, PRIMASK", out(reg) primask, options(nomem, nostack));
+        }
+        primask & 1 != 0
+    }
+    #[cfg(not(target_arch = "arm"))]
+    false
+}
+
+// run f with all interrupts masked, nesting is allowed
+pub fn free<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let disabled = is_disabled();
+    disable();
+    let result = f();
+    if !disabled {
+        unsafe { enable() };
+    }
+    result
+}
+
+pub fn get_basepri() -> u8 {
+    #[cfg(target_arch = "arm")]
+    {
+        let basepri: u32;
+        unsafe {
+            asm!("mrs {}, BASEPRI", out(reg) basepri, options(nomem, nostack));
+        }
+        basepri as u8
+    }
+    #[cfg(not(target_arch = "arm"))]
+    0
+}
+
+/// Masks the interrupts whose priority value is `priority` or higher, 0 turns off the masking.
+///
+/// # Safety
+///
+/// Only the upper priority bits implemented by the NVIC are compared, e.g. the upper 4 bits on
+/// the STM32F4, so `priority` must be a multiple of the priority step of the device and
+/// values below one step turn the masking off as well. The processor ignores the write in
+/// unprivileged mode, so it may only be called in privileged mode, and lowering the mask ends
+/// a critical section of the caller.
+#[cfg_attr(not(target_arch = "arm"), allow(unused_variables))]
+pub unsafe fn set_basepri(priority: u8) {
+    #[cfg(target_arch = "arm")]
+    asm!("msr BASEPRI, {}", "isb", in(reg) priority as u32, options(nomem, nostack));
+}
+
+// run f with the interrupts of `priority` or lower masked,
+// a stricter mask which is already active is kept
+#[cfg_attr(not(target_arch = "arm"), allow(unused_variables))]
+pub fn mask<F, R>(priority: u8, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let basepri = get_basepri();
+    #[cfg(target_arch = "arm")]
+    unsafe {
+        asm!("msr BASEPRI_MAX, {}", "isb", in(reg) priority as u32, options(nomem, nostack));
+    }
+    let result = f();
+    unsafe { set_basepri(basepri) };
+    result
+}
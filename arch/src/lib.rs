@@ -1,8 +1,23 @@
 #![no_std]
+#![feature(asm)]
 
+pub mod dwt;
+pub mod fpu;
+pub mod interrupt;
+pub mod mpu;
 pub mod nvic;
+pub mod scb;
 pub mod systick;
 
+// EXC_RETURN values which return to thread mode on the process stack
+pub const EXC_RETURN_PROCESS: u32 = 0xffff_fffd;
+pub const EXC_RETURN_PROCESS_FPU: u32 = 0xffff_ffed;
+
+// a cleared bit 4 of EXC_RETURN means the frame is an ExtendedStackFrame
+pub fn is_extended_frame(exc_return: u32) -> bool {
+    exc_return & (1 << 4) == 0
+}
+
 #[repr(C)]
 pub struct StackFrame {
     pub r0: u32,
@@ -16,10 +31,51 @@ pub struct StackFrame {
 }
 
 impl StackFrame {
+    /// # Safety
+    ///
+    /// `sp` must point to an exception frame stacked by the processor, such as the saved stack
+    /// pointer of a process, and the frame must stay valid and unaliased for `'a`.
     pub unsafe fn from_ptr_mut<'a>(sp: *const u32) -> &'a mut StackFrame {
         &mut *(sp as *mut StackFrame)
     }
+
+    /// # Safety
+    ///
+    /// `sp` must point to an exception frame stacked by the processor which stays valid for `'a`.
     pub unsafe fn from_ptr<'a>(sp: *const u32) -> &'a StackFrame {
         &*(sp as *const StackFrame)
     }
 }
+
+// stacked instead of StackFrame while the FPU context is active
+#[repr(C)]
+pub struct ExtendedStackFrame {
+    pub frame: StackFrame,
+    pub s: [u32; 16],
+    pub fpscr: u32,
+    _reserved: u32,
+}
+
+impl ExtendedStackFrame {
+    /// # Safety
+    ///
+    /// `sp` must point to an exception frame stacked with the FPU context, see
+    /// `is_extended_frame`, and the frame must stay valid and unaliased for `'a`.
+    pub unsafe fn from_ptr_mut<'a>(sp: *const u32) -> &'a mut ExtendedStackFrame {
+        &mut *(sp as *mut ExtendedStackFrame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem;
+
+    #[test]
+    fn test_stack_frame() {
+        assert_eq!(0x20, mem::size_of::<StackFrame>());
+        assert_eq!(0x68, mem::size_of::<ExtendedStackFrame>());
+        assert!(!is_extended_frame(EXC_RETURN_PROCESS));
+        assert!(is_extended_frame(EXC_RETURN_PROCESS_FPU));
+    }
+}
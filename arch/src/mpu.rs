@@ -0,0 +1,228 @@
+use crate::scb::{Scb, SystemHandler};
+use volatile_register::{RO, RW};
+
+const CTRL_ENABLE: u32 = 1 << 0;
+const CTRL_PRIVDEFENA: u32 = 1 << 2;
+const RASR_ENABLE: u32 = 1 << 0;
+const RASR_XN: u32 = 1 << 28;
+const MIN_REGION_SIZE: u32 = 32;
+
+#[repr(C)]
+pub struct MpuRegisters {
+    pub typer: RO<u32>,
+    pub ctrl: RW<u32>,
+    pub rnr: RW<u32>,
+    pub rbar: RW<u32>,
+    pub rasr: RW<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AccessPermission {
+    NoAccess = 0b000,
+    PrivilegedOnly = 0b001,
+    UnprivilegedReadOnly = 0b010,
+    FullAccess = 0b011,
+    PrivilegedReadOnly = 0b101,
+    ReadOnly = 0b110,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MemoryType {
+    // cacheable write-back memory such as flash and SRAM
+    Normal,
+    // shareable device memory for peripherals
+    Device,
+}
+
+impl MemoryType {
+    // TEX, S, C and B bits of RASR
+    fn attributes(&self) -> u32 {
+        match self {
+            MemoryType::Normal => (1 << 17) | (1 << 16),
+            MemoryType::Device => (1 << 18) | (1 << 16),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Region {
+    base: u32,
+    rasr: u32,
+}
+
+impl Region {
+    // the size must be a power of two of at least 32 bytes and the base must be aligned to it
+    pub fn new(
+        base: u32,
+        size: u32,
+        access: AccessPermission,
+        memory_type: MemoryType,
+        executable: bool,
+    ) -> Option<Region> {
+        if size < MIN_REGION_SIZE || !size.is_power_of_two() || base % size != 0 {
+            return None;
+        }
+        let size_field = size.trailing_zeros() - 1;
+        let xn = if executable { 0 } else { RASR_XN };
+        let rasr =
+            xn | (access as u32) << 24 | memory_type.attributes() | size_field << 1 | RASR_ENABLE;
+        Some(Region { base, rasr })
+    }
+
+    // the smallest region which contains the area between start and end
+    pub fn covering(
+        start: u32,
+        end: u32,
+        access: AccessPermission,
+        memory_type: MemoryType,
+        executable: bool,
+    ) -> Option<Region> {
+        let mut size = MIN_REGION_SIZE as u64;
+        while size < 1 << 32 {
+            let base = start as u64 & !(size - 1);
+            if base + size >= end as u64 {
+                return Region::new(base as u32, size as u32, access, memory_type, executable);
+            }
+            size <<= 1;
+        }
+        None
+    }
+
+    pub fn base(&self) -> u32 {
+        self.base
+    }
+
+    pub fn size(&self) -> u32 {
+        2 << ((self.rasr >> 1) & 0x1f)
+    }
+
+    pub fn access(&self) -> u32 {
+        (self.rasr >> 24) & 0b111
+    }
+
+    pub fn is_executable(&self) -> bool {
+        self.rasr & RASR_XN == 0
+    }
+
+    pub fn contains(&self, addr: u32, len: u32) -> bool {
+        let end = self.base as u64 + self.size() as u64;
+        addr >= self.base && addr as u64 + len as u64 <= end
+    }
+
+    // access from unprivileged code
+    pub fn is_readable(&self) -> bool {
+        matches!(self.access(), 0b010 | 0b011 | 0b110 | 0b111)
+    }
+
+    pub fn is_writable(&self) -> bool {
+        self.access() == AccessPermission::FullAccess as u32
+    }
+}
+
+pub struct Mpu;
+
+impl Mpu {
+    pub const fn new() -> Mpu {
+        Mpu {}
+    }
+
+    pub fn get_registers_ref(&self) -> &MpuRegisters {
+        let registers = 0xE000_ED90 as *mut MpuRegisters;
+        unsafe { &*registers }
+    }
+
+    pub fn region_count(&self) -> u32 {
+        (self.get_registers_ref().typer.read() >> 8) & 0xff
+    }
+
+    // privileged code keeps the default memory map for areas without any region
+    // the caller has to issue dsb and isb after enabling
+    pub fn enable(&self) {
+        let registers = self.get_registers_ref();
+        Scb::new().enable_fault_handler(SystemHandler::MemManage);
+        unsafe {
+            registers.ctrl.write(CTRL_PRIVDEFENA | CTRL_ENABLE);
+        }
+    }
+
+    pub fn disable(&self) {
+        let registers = self.get_registers_ref();
+        unsafe {
+            registers.ctrl.write(0);
+        }
+    }
+
+    pub fn set_region(&self, number: u32, region: &Region) {
+        let registers = self.get_registers_ref();
+        unsafe {
+            registers.rnr.write(number);
+            registers.rasr.write(0);
+            registers.rbar.write(region.base);
+            registers.rasr.write(region.rasr);
+        }
+    }
+
+    pub fn clear_region(&self, number: u32) {
+        let registers = self.get_registers_ref();
+        unsafe {
+            registers.rnr.write(number);
+            registers.rasr.write(0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_region() {
+        let region = Region::new(
+            0x2000_0400,
+            1024,
+            AccessPermission::FullAccess,
+            MemoryType::Normal,
+            false,
+        )
+        .unwrap();
+        assert_eq!(0x2000_0400, region.base());
+        assert_eq!(1024, region.size());
+        assert_eq!(AccessPermission::FullAccess as u32, region.access());
+        assert!(!region.is_executable());
+        assert_eq!(0x1303_0013, region.rasr);
+        assert!(region.contains(0x2000_0400, 1024));
+        assert!(region.contains(0x2000_07fc, 4));
+        assert!(!region.contains(0x2000_07fc, 8));
+        assert!(!region.contains(0x2000_03fc, 4));
+        assert!(region.is_readable() && region.is_writable());
+
+        let access = AccessPermission::ReadOnly;
+        let region = Region::new(0x2000_0000, 1024, access, MemoryType::Normal, true).unwrap();
+        assert!(region.is_readable() && !region.is_writable());
+        let access = AccessPermission::PrivilegedOnly;
+        let region = Region::new(0x2000_0000, 1024, access, MemoryType::Normal, true).unwrap();
+        assert!(!region.is_readable() && !region.is_writable());
+        assert!(Region::new(0x2000_0200, 1024, access, MemoryType::Normal, true).is_none());
+        assert!(Region::new(0x2000_0000, 1000, access, MemoryType::Normal, true).is_none());
+        assert!(Region::new(0x2000_0000, 16, access, MemoryType::Normal, true).is_none());
+    }
+
+    #[test]
+    fn test_covering() {
+        let access = AccessPermission::FullAccess;
+        let region =
+            Region::covering(0x2000_0010, 0x2000_0130, access, MemoryType::Normal, false).unwrap();
+        assert_eq!(0x2000_0000, region.base());
+        assert_eq!(512, region.size());
+
+        let region =
+            Region::covering(0x2000_01f0, 0x2000_0210, access, MemoryType::Normal, false).unwrap();
+        assert_eq!(0x2000_0000, region.base());
+        assert_eq!(1024, region.size());
+
+        let region = Region::covering(0, 0x0004_0000, access, MemoryType::Normal, true).unwrap();
+        assert_eq!(0, region.base());
+        assert_eq!(0x0004_0000, region.size());
+        assert!(region.is_executable());
+    }
+}
@@ -58,4 +58,16 @@ impl Nvic {
             registers.icpr[idx].write(1 << (id % 32));
         }
     }
+
+    // only the implemented upper bits of the priority are kept
+    pub fn set_priority(&self, id: u32, priority: u8) {
+        let registers = self.get_registers_ref();
+        unsafe {
+            registers.ipr[id as usize].write(priority);
+        }
+    }
+
+    pub fn get_priority(&self, id: u32) -> u8 {
+        self.get_registers_ref().ipr[id as usize].read()
+    }
 }
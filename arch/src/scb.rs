@@ -0,0 +1,362 @@
+use volatile_register::{RO, RW};
+
+const ICSR_PENDSVSET: u32 = 1 << 28;
+const ICSR_PENDSVCLR: u32 = 1 << 27;
+const AIRCR_VECTKEY: u32 = 0x05fa << 16;
+const AIRCR_PRIGROUP_SHIFT: u32 = 8;
+const AIRCR_PRIGROUP_MASK: u32 = 0b111 << AIRCR_PRIGROUP_SHIFT;
+const AIRCR_SYSRESETREQ: u32 = 1 << 2;
+const SCR_SLEEPONEXIT: u32 = 1 << 1;
+const SCR_SLEEPDEEP: u32 = 1 << 2;
+const CCR_UNALIGN_TRP: u32 = 1 << 3;
+const CCR_DIV_0_TRP: u32 = 1 << 4;
+const VTOR_MASK: u32 = !0x7f;
+const HFSR_VECTTBL: u32 = 1 << 1;
+const HFSR_FORCED: u32 = 1 << 30;
+const MMARVALID: u32 = 1 << 7;
+const BFARVALID: u32 = 1 << 15;
+
+#[repr(C)]
+pub struct ScbRegisters {
+    pub cpuid: RO<u32>,
+    pub icsr: RW<u32>,
+    pub vtor: RW<u32>,
+    pub aircr: RW<u32>,
+    pub scr: RW<u32>,
+    pub ccr: RW<u32>,
+    pub shpr: [RW<u8>; 12],
+    pub shcsr: RW<u32>,
+    pub cfsr: RW<u32>,
+    pub hfsr: RW<u32>,
+    pub dfsr: RW<u32>,
+    pub mmfar: RW<u32>,
+    pub bfar: RW<u32>,
+    pub afsr: RW<u32>,
+}
+
+// system handlers with a configurable priority, numbered by exception number
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SystemHandler {
+    MemManage = 4,
+    BusFault = 5,
+    UsageFault = 6,
+    SVCall = 11,
+    PendSV = 14,
+    SysTick = 15,
+}
+
+impl SystemHandler {
+    fn shpr_index(&self) -> usize {
+        *self as usize - 4
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fault {
+    InstructionAccessViolation,
+    DataAccessViolation,
+    UnstackingMemoryFault,
+    StackingMemoryFault,
+    LazyFpMemoryFault,
+    InstructionBusError,
+    PreciseDataBusError,
+    ImpreciseDataBusError,
+    UnstackingBusError,
+    StackingBusError,
+    LazyFpBusError,
+    UndefinedInstruction,
+    InvalidState,
+    InvalidPc,
+    NoCoprocessor,
+    UnalignedAccess,
+    DivideByZero,
+    VectorTableRead,
+    Unknown,
+}
+
+const CFSR_FAULTS: [(u32, Fault); 17] = [
+    (1 << 0, Fault::InstructionAccessViolation),
+    (1 << 1, Fault::DataAccessViolation),
+    (1 << 3, Fault::UnstackingMemoryFault),
+    (1 << 4, Fault::StackingMemoryFault),
+    (1 << 5, Fault::LazyFpMemoryFault),
+    (1 << 8, Fault::InstructionBusError),
+    (1 << 9, Fault::PreciseDataBusError),
+    (1 << 10, Fault::ImpreciseDataBusError),
+    (1 << 11, Fault::UnstackingBusError),
+    (1 << 12, Fault::StackingBusError),
+    (1 << 13, Fault::LazyFpBusError),
+    (1 << 16, Fault::UndefinedInstruction),
+    (1 << 17, Fault::InvalidState),
+    (1 << 18, Fault::InvalidPc),
+    (1 << 19, Fault::NoCoprocessor),
+    (1 << 24, Fault::UnalignedAccess),
+    (1 << 25, Fault::DivideByZero),
+];
+
+impl Fault {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Fault::InstructionAccessViolation => "instruction access violation",
+            Fault::DataAccessViolation => "data access violation",
+            Fault::UnstackingMemoryFault => "unstacking memory fault",
+            Fault::StackingMemoryFault => "stacking memory fault",
+            Fault::LazyFpMemoryFault => "lazy fp stacking memory fault",
+            Fault::InstructionBusError => "instruction bus error",
+            Fault::PreciseDataBusError => "precise data bus error",
+            Fault::ImpreciseDataBusError => "imprecise data bus error",
+            Fault::UnstackingBusError => "unstacking bus error",
+            Fault::StackingBusError => "stacking bus error",
+            Fault::LazyFpBusError => "lazy fp stacking bus error",
+            Fault::UndefinedInstruction => "undefined instruction",
+            Fault::InvalidState => "invalid state",
+            Fault::InvalidPc => "invalid pc",
+            Fault::NoCoprocessor => "no coprocessor",
+            Fault::UnalignedAccess => "unaligned access",
+            Fault::DivideByZero => "divide by zero",
+            Fault::VectorTableRead => "vector table read error",
+            Fault::Unknown => "unknown fault",
+        }
+    }
+}
+
+pub struct FaultStatus {
+    pub cfsr: u32,
+    pub hfsr: u32,
+    pub mmfar: Option<u32>,
+    pub bfar: Option<u32>,
+}
+
+impl FaultStatus {
+    // the fault addresses are only kept when CFSR marks them as valid
+    pub fn new(cfsr: u32, hfsr: u32, mmfar: u32, bfar: u32) -> FaultStatus {
+        FaultStatus {
+            cfsr,
+            hfsr,
+            mmfar: if cfsr & MMARVALID != 0 {
+                Some(mmfar)
+            } else {
+                None
+            },
+            bfar: if cfsr & BFARVALID != 0 {
+                Some(bfar)
+            } else {
+                None
+            },
+        }
+    }
+
+    // a forced HardFault reports the escalated fault in CFSR
+    pub fn fault(&self) -> Fault {
+        match CFSR_FAULTS.iter().find(|(bit, _)| self.cfsr & bit != 0) {
+            Some((_, fault)) => *fault,
+            None if self.hfsr & HFSR_VECTTBL != 0 => Fault::VectorTableRead,
+            None => Fault::Unknown,
+        }
+    }
+
+    pub fn is_forced(&self) -> bool {
+        self.hfsr & HFSR_FORCED != 0
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.fault().description()
+    }
+}
+
+pub struct Scb;
+
+impl Scb {
+    pub const fn new() -> Scb {
+        Scb {}
+    }
+
+    pub fn get_registers_ref(&self) -> &ScbRegisters {
+        let registers = 0xE000_ED00 as *mut ScbRegisters;
+        unsafe { &*registers }
+    }
+
+    pub fn set_pendsv(&self) {
+        let registers = self.get_registers_ref();
+        unsafe {
+            registers.icsr.write(ICSR_PENDSVSET);
+        }
+    }
+
+    pub fn clear_pendsv(&self) {
+        let registers = self.get_registers_ref();
+        unsafe {
+            registers.icsr.write(ICSR_PENDSVCLR);
+        }
+    }
+
+    pub fn is_pendsv_pending(&self) -> bool {
+        self.get_registers_ref().icsr.read() & ICSR_PENDSVSET != 0
+    }
+
+    // only the implemented upper bits of the priority are kept
+    pub fn set_priority(&self, handler: SystemHandler, priority: u8) {
+        let registers = self.get_registers_ref();
+        unsafe {
+            registers.shpr[handler.shpr_index()].write(priority);
+        }
+    }
+
+    pub fn get_priority(&self, handler: SystemHandler) -> u8 {
+        self.get_registers_ref().shpr[handler.shpr_index()].read()
+    }
+
+    // priority bits below bit `group + 1` are the subpriority, which does not preempt
+    pub fn set_priority_grouping(&self, group: u32) {
+        let registers = self.get_registers_ref();
+        unsafe {
+            registers
+                .aircr
+                .write(AIRCR_VECTKEY | ((group << AIRCR_PRIGROUP_SHIFT) & AIRCR_PRIGROUP_MASK));
+        }
+    }
+
+    pub fn get_priority_grouping(&self) -> u32 {
+        (self.get_registers_ref().aircr.read() & AIRCR_PRIGROUP_MASK) >> AIRCR_PRIGROUP_SHIFT
+    }
+
+    pub fn system_reset(&self) -> ! {
+        let registers = self.get_registers_ref();
+        let grouping = registers.aircr.read() & AIRCR_PRIGROUP_MASK;
+        unsafe {
+            #[cfg(target_arch = "arm")]
+            asm!("dsb", options(nomem, nostack));
+            registers
+                .aircr
+                .write(AIRCR_VECTKEY | grouping | AIRCR_SYSRESETREQ);
+            #[cfg(target_arch = "arm")]
+            asm!("dsb", options(nomem, nostack));
+        }
+        // the reset request takes effect after a few cycles
+        loop {
+            #[cfg(target_arch = "arm")]
+            unsafe {
+                asm!("wfi", options(nomem, nostack));
+            }
+            #[cfg(not(target_arch = "arm"))]
+            core::hint::spin_loop();
+        }
+    }
+
+    // the table must be aligned to 128 bytes
+    pub fn set_vector_table(&self, addr: u32) {
+        let registers = self.get_registers_ref();
+        unsafe {
+            registers.vtor.write(addr & VTOR_MASK);
+        }
+    }
+
+    pub fn get_vector_table(&self) -> u32 {
+        self.get_registers_ref().vtor.read()
+    }
+
+    pub fn set_sleepdeep(&self, enabled: bool) {
+        self.set_scr(SCR_SLEEPDEEP, enabled);
+    }
+
+    // the processor sleeps again when it returns from a handler to thread mode
+    pub fn set_sleep_on_exit(&self, enabled: bool) {
+        self.set_scr(SCR_SLEEPONEXIT, enabled);
+    }
+
+    fn set_scr(&self, bit: u32, enabled: bool) {
+        let registers = self.get_registers_ref();
+        unsafe {
+            registers
+                .scr
+                .modify(|val| if enabled { val | bit } else { val & !bit });
+        }
+    }
+
+    // without it the fault escalates to HardFault
+    pub fn enable_fault_handler(&self, handler: SystemHandler) {
+        let bit = match handler {
+            SystemHandler::MemManage => 1 << 16,
+            SystemHandler::BusFault => 1 << 17,
+            SystemHandler::UsageFault => 1 << 18,
+            _ => panic!("not a fault handler"),
+        };
+        let registers = self.get_registers_ref();
+        unsafe {
+            registers.shcsr.modify(|val| val | bit);
+        }
+    }
+
+    pub fn enable_unaligned_trap(&self) {
+        let registers = self.get_registers_ref();
+        unsafe {
+            registers.ccr.modify(|val| val | CCR_UNALIGN_TRP);
+        }
+    }
+
+    pub fn enable_div_by_zero_trap(&self) {
+        let registers = self.get_registers_ref();
+        unsafe {
+            registers.ccr.modify(|val| val | CCR_DIV_0_TRP);
+        }
+    }
+
+    pub fn fault_status(&self) -> FaultStatus {
+        let registers = self.get_registers_ref();
+        FaultStatus::new(
+            registers.cfsr.read(),
+            registers.hfsr.read(),
+            registers.mmfar.read(),
+            registers.bfar.read(),
+        )
+    }
+
+    // read the fault status and clear it for the next fault
+    pub fn take_fault_status(&self) -> FaultStatus {
+        let registers = self.get_registers_ref();
+        let status = self.fault_status();
+        unsafe {
+            registers.cfsr.write(status.cfsr);
+            registers.hfsr.write(status.hfsr);
+        }
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem;
+
+    #[test]
+    fn test_register_layout() {
+        assert_eq!(0x40, mem::size_of::<ScbRegisters>());
+        assert_eq!(10, SystemHandler::PendSV.shpr_index());
+        assert_eq!(11, SystemHandler::SysTick.shpr_index());
+    }
+
+    #[test]
+    fn test_fault_status() {
+        let status = FaultStatus::new(MMARVALID | (1 << 1), 0, 0x2000_0000, 0x4000_0000);
+        assert_eq!(Fault::DataAccessViolation, status.fault());
+        assert_eq!("data access violation", status.name());
+        assert_eq!(Some(0x2000_0000), status.mmfar);
+        assert_eq!(None, status.bfar);
+
+        let status = FaultStatus::new(BFARVALID | (1 << 9), HFSR_FORCED, 0, 0x4000_0000);
+        assert_eq!(Fault::PreciseDataBusError, status.fault());
+        assert!(status.is_forced());
+        assert_eq!(None, status.mmfar);
+        assert_eq!(Some(0x4000_0000), status.bfar);
+
+        assert_eq!(
+            Fault::DivideByZero,
+            FaultStatus::new(1 << 25, 0, 0, 0).fault()
+        );
+        assert_eq!(
+            Fault::VectorTableRead,
+            FaultStatus::new(0, HFSR_VECTTBL, 0, 0).fault()
+        );
+        assert_eq!("unknown fault", FaultStatus::new(0, 0, 0, 0).name());
+    }
+}
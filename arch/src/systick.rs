@@ -1,6 +1,9 @@
 use core::ops::Deref;
 use volatile_register::{RO, RW};
 
+const CSR_ENABLE: u32 = 1 << 0;
+const CSR_COUNTFLAG: u32 = 1 << 16;
+
 #[repr(C)]
 pub struct SystickRegisters {
     pub csr: RW<u32>,
@@ -41,9 +44,29 @@ impl Systick {
         }
     }
 
+    pub fn get_reload(&self) -> u32 {
+        self.rvr.read()
+    }
+
+    pub fn get_current(&self) -> u32 {
+        self.cvr.read()
+    }
+
     pub fn enable(&self) {
         unsafe {
             self.csr.modify(|val| (val | 0x3));
         }
     }
+
+    // the counter keeps its value until it is enabled again
+    pub fn disable(&self) {
+        unsafe {
+            self.csr.modify(|val| val & !CSR_ENABLE);
+        }
+    }
+
+    // whether the counter reached 0 since the last read of CSR, reading clears the flag
+    pub fn has_wrapped(&self) -> bool {
+        self.csr.read() & CSR_COUNTFLAG != 0
+    }
 }
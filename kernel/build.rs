@@ -6,8 +6,23 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     println!("cargo:rustc-link-search={}", out_dir.display());
 
+    // the context switch is only needed on the target, host builds run the tests
+    if env::var("CARGO_CFG_TARGET_ARCH").map_or(true, |arch| arch != "arm") {
+        return Ok(());
+    }
+
+    // the fpu feature also saves the FPU context of the processes, but only on hard-float targets
+    // since the feature is unified with the other packages of the workspace
+    let fpu = env::var_os("CARGO_FEATURE_FPU").is_some()
+        && env::var("TARGET").map_or(false, |target| target.ends_with("eabihf"));
+    let file = if fpu {
+        println!("cargo:rustc-cfg=fpu");
+        "src/asm_fpu.s"
+    } else {
+        "src/asm.s"
+    };
     // `asm.s`ファイルをアセンブルします
-    Build::new().file("src/asm.s").compile("asm");
+    Build::new().file(file).compile("asm");
 
     Ok(())
 }
\ No newline at end of file
@@ -10,9 +10,10 @@ asm_execute_process:
     push {r8, r9, r10, r11}
     msr psp, r0
     ldmia r1, {r4-r11}
-    cpsie i
+    dsb
+    isb
+    ldr r3, =0xfffffffd
     svc 0
-    cpsid i
     stmia r1, {r4-r11}
     mrs r0, psp
     pop {r8, r9, r10, r11}
@@ -0,0 +1,40 @@
+.cpu cortex-m4
+.fpu fpv4-sp-d16
+.thumb
+
+.section .text
+.syntax unified
+.global asm_execute_process
+
+@ r2 points to s16-s31 of the process followed by its EXC_RETURN
+asm_execute_process:
+    push {r4, r5, r6, r7, lr}
+    push {r8, r9, r10, r11}
+    vpush {s16-s31}
+    msr psp, r0
+    ldr r3, [r2, #64]
+    tst r3, #0x10
+    bne 1f
+    vldmia r2, {s16-s31}
+1:
+    @ enter the process with a basic frame on the kernel stack
+    mrs r12, CONTROL
+    bic r12, r12, #4
+    msr CONTROL, r12
+    ldmia r1, {r4-r11}
+    dsb
+    isb
+    svc 0
+    stmia r1, {r4-r11}
+    ldr r3, =PROCESS_EXC_RETURN
+    ldr r3, [r3]
+    str r3, [r2, #64]
+    tst r3, #0x10
+    bne 2f
+    @ also triggers the lazy stacking of s0-s15 into the frame of the process
+    vstmia r2, {s16-s31}
+2:
+    mrs r0, psp
+    vpop {s16-s31}
+    pop {r8, r9, r10, r11}
+    pop {r4, r5, r6, r7, pc}
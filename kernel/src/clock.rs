@@ -0,0 +1,126 @@
+use arch::systick::Systick;
+use core::ptr;
+
+const MAX_RELOAD: u32 = 0x00ff_ffff;
+
+// ticks since the clock started, only the SysTick handler writes it
+static mut TICKS: u64 = 0;
+
+pub struct Clock {
+    tick_hz: u32,
+    reload: u32,
+}
+
+impl Clock {
+    // source_hz is the frequency of the SysTick clock, the core clock by default
+    pub fn new(source_hz: u32, tick_hz: u32) -> Clock {
+        let reload = if tick_hz == 0 { 0 } else { source_hz / tick_hz };
+        if reload == 0 || reload - 1 > MAX_RELOAD {
+            panic!("invalid tick rate");
+        }
+        Clock { tick_hz, reload }
+    }
+
+    // the calibration value of SysTick is the count for 10ms
+    pub fn from_calibration(tick_hz: u32) -> Clock {
+        Clock::new(Systick::new().get_ticks_per_10ms() * 100, tick_hz)
+    }
+
+    pub fn tick_hz(&self) -> u32 {
+        self.tick_hz
+    }
+
+    pub fn start(&self) {
+        let systick = Systick::new();
+        systick.set_reload(self.reload - 1);
+        systick.clear_current();
+        systick.enable();
+    }
+
+    // the most ticks one SysTick period can be stretched to
+    fn max_idle_ticks(&self) -> u32 {
+        (MAX_RELOAD + 1) / self.reload
+    }
+
+    // stretch the current SysTick period so the next interrupt comes `ticks` ticks later,
+    // returns the ticks it was stretched to or 0 when it was left alone
+    pub fn suspend(&self, ticks: u64) -> u32 {
+        let idle = ticks.min(self.max_idle_ticks() as u64) as u32;
+        if idle < 2 {
+            return 0;
+        }
+        let systick = Systick::new();
+        systick.disable();
+        // the rest of the current tick and the skipped ticks
+        let cycles = systick.get_current() + (idle - 1) * self.reload;
+        systick.set_reload(cycles - 1);
+        systick.clear_current();
+        systick.enable();
+        idle
+    }
+
+    // count the ticks which passed since `suspend` and go back to the tick period,
+    // it must run before the SysTick handler of the wakeup.
+    // the part of a tick which passed before an early wakeup is lost
+    pub fn resume(&self, idle: u32) {
+        if idle == 0 {
+            return;
+        }
+        let systick = Systick::new();
+        let wrapped = systick.has_wrapped();
+        systick.disable();
+        let passed = if wrapped {
+            // the pending SysTick handler counts the last one
+            idle - 1
+        } else {
+            passed_ticks(idle, systick.get_current(), self.reload)
+        };
+        unsafe { add_ticks(passed as u64) };
+        self.start();
+    }
+}
+
+// the ticks still left are the ones which start within the remaining cycles
+fn passed_ticks(idle: u32, remaining: u32, reload: u32) -> u32 {
+    idle - (remaining + reload - 1) / reload
+}
+
+// SysTick must not preempt the caller, the kernel masks it with BASEPRI
+pub fn ticks() -> u64 {
+    unsafe { ptr::read_volatile(&TICKS) }
+}
+
+#[cfg(target_arch = "arm")]
+pub(crate) unsafe fn tick() {
+    add_ticks(1);
+}
+
+unsafe fn add_ticks(ticks: u64) {
+    ptr::write_volatile(&mut TICKS, TICKS + ticks);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reload() {
+        let clock = Clock::new(168_000_000, 1000);
+        assert_eq!(168_000, clock.reload);
+        assert_eq!(1000, clock.tick_hz());
+        assert_eq!(MAX_RELOAD + 1, Clock::new(MAX_RELOAD + 1, 1).reload);
+    }
+
+    #[test]
+    fn test_idle_ticks() {
+        assert_eq!(99, Clock::new(168_000_000, 1000).max_idle_ticks());
+        assert_eq!(1, Clock::new(MAX_RELOAD + 1, 1).max_idle_ticks());
+
+        // stretched to 10 ticks with 500 cycles left of the first
+        let cycles = 500 + 9 * 1000;
+        assert_eq!(0, passed_ticks(10, cycles, 1000));
+        assert_eq!(0, passed_ticks(10, 9001, 1000));
+        assert_eq!(1, passed_ticks(10, 9000, 1000));
+        assert_eq!(9, passed_ticks(10, 1, 1000));
+    }
+}
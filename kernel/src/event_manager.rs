@@ -0,0 +1,102 @@
+use crate::process::Process;
+use crate::process_list::{ProcessList, ProcessListItem};
+use crate::process_manager::ProcessId;
+use crate::sys_error::SUCCESS;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum WaitMode {
+    Any = 0,
+    All = 1,
+}
+
+impl WaitMode {
+    pub fn from_u32(mode: u32) -> WaitMode {
+        if mode == WaitMode::All as u32 {
+            WaitMode::All
+        } else {
+            WaitMode::Any
+        }
+    }
+}
+
+// clear and return the flags in the mask if the condition is satisfied
+pub fn take_flags(flags: &mut u32, mask: u32, mode: WaitMode) -> Option<u32> {
+    let matched = *flags & mask;
+    let satisfied = match mode {
+        WaitMode::Any => matched != 0,
+        WaitMode::All => mask != 0 && matched == mask,
+    };
+    if satisfied {
+        *flags &= !matched;
+        Some(matched)
+    } else {
+        None
+    }
+}
+
+pub struct EventManager<'a> {
+    // processes blocked in wait_flags, the mask and the mode are kept in their frame
+    waiting: ProcessList<'a>,
+}
+
+impl<'a> EventManager<'a> {
+    pub fn new() -> EventManager<'a> {
+        EventManager {
+            waiting: ProcessList::new(),
+        }
+    }
+
+    pub fn wait(&mut self, item: &'a mut ProcessListItem<'a>) {
+        self.waiting.push(item);
+    }
+
+    // post the flags and return the process if its wait is satisfied
+    pub fn notify(
+        &mut self,
+        id: &ProcessId,
+        process: &mut Process<'a>,
+        flags: u32,
+    ) -> Option<&'a mut ProcessListItem<'a>> {
+        process.event_flags |= flags;
+        if !self.waiting.iter().any(|item| item == id) {
+            return None;
+        }
+        let (mask, mode) = {
+            let frame = process.frame_mut();
+            (frame.r1, WaitMode::from_u32(frame.r2))
+        };
+        let matched = take_flags(&mut process.event_flags, mask, mode)?;
+        let frame = process.frame_mut();
+        frame.r0 = SUCCESS;
+        frame.r1 = matched;
+        self.waiting.remove_by(|item| item == id)
+    }
+
+    pub fn remove_wait(&mut self, id: &ProcessId) -> Option<&'a mut ProcessListItem<'a>> {
+        self.waiting.remove_by(|item| item == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_any() {
+        let mut flags = 0b1010;
+        assert_eq!(None, take_flags(&mut flags, 0b0101, WaitMode::Any));
+        assert_eq!(Some(0b0010), take_flags(&mut flags, 0b0011, WaitMode::Any));
+        assert_eq!(0b1000, flags);
+        assert_eq!(None, take_flags(&mut flags, 0, WaitMode::Any));
+    }
+
+    #[test]
+    fn test_take_all() {
+        let mut flags = 0b1010;
+        assert_eq!(None, take_flags(&mut flags, 0b0011, WaitMode::All));
+        assert_eq!(0b1010, flags);
+        assert_eq!(Some(0b1010), take_flags(&mut flags, 0b1010, WaitMode::All));
+        assert_eq!(0, flags);
+        assert_eq!(None, take_flags(&mut flags, 0, WaitMode::All));
+    }
+}
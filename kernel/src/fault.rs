@@ -0,0 +1,65 @@
+use arch::scb::Scb;
+use arch::StackFrame;
+use log::dhprintln;
+
+// set when a process faulted, the kernel terminates the current process
+#[no_mangle]
+pub static mut FAULT_FIRED: u32 = 0;
+
+macro_rules! fault_handler {
+    ($name:ident) => {
+        /// # Safety
+        /// Only the processor calls it, as the handler of the fault.
+        #[no_mangle]
+        #[naked]
+        pub unsafe extern "C" fn $name() {
+            asm!("b {}", sym fault_entry, options(noreturn));
+        }
+    };
+}
+
+fault_handler!(HardFault);
+fault_handler!(MemManage);
+fault_handler!(BusFault);
+fault_handler!(UsageFault);
+
+// faults from a process return to the kernel like SysTick, others are fatal
+#[naked]
+unsafe extern "C" fn fault_entry() {
+    asm!(
+        "tst lr, #4",
+        "mrs r0, msp",
+        "beq {}",
+        "ldr r0, =FAULT_FIRED",
+        "mov r1, #1",
+        "str r1, [r0, #0]",
+        "ldr r0, =PROCESS_EXC_RETURN",
+        "str lr, [r0, #0]",
+        "mrs r0, CONTROL",
+        "bic r0, r0, #1",
+        "msr CONTROL, r0",
+        "isb",
+        "movw lr, #0xfff9",
+        "movt lr, #0xffff",
+        "bx lr",
+        sym kernel_fault,
+        options(noreturn),
+    );
+}
+
+unsafe extern "C" fn kernel_fault(sp: *const u32) -> ! {
+    let frame = StackFrame::from_ptr(sp);
+    let status = Scb::new().take_fault_status();
+    dhprintln!(
+        "kernel fault at {:x}: {} (cfsr {:x}, hfsr {:x}, mmfar {:x?}, bfar {:x?})",
+        frame.return_addr,
+        status.name(),
+        status.cfsr,
+        status.hfsr,
+        status.mmfar,
+        status.bfar
+    );
+    loop {
+        asm!("wfi", options(nomem, nostack));
+    }
+}
@@ -1,86 +1,186 @@
 use crate::process_list::{ProcessList, ProcessListItem};
+use crate::process_manager::ProcessId;
+use crate::stats::{cycle_count, cycles_since, Stats};
 use arch::nvic::Nvic;
-use core::mem;
+use core::sync::atomic::{AtomicU32, Ordering};
+#[cfg(target_arch = "arm")]
 use rt::Vector;
 
+const IRQ_COUNT: usize = 240;
+// the kernel masks this priority, interrupts with a higher priority are never delayed by it
+pub const IRQ_PRIORITY: u8 = 0x80;
+const NO_HANDLER: u8 = u8::MAX;
+
+#[cfg(target_arch = "arm")]
 extern "C" {
-    static mut IRQS: [Vector; 240];
+    static mut IRQS: [Vector; IRQ_COUNT];
 }
 
+// set by DefaultIrqHandler, the kernel takes them in check_pending
+#[no_mangle]
+pub static PENDING_IRQS: [AtomicU32; IRQ_COUNT / 32] = [NO_PENDING; IRQ_COUNT / 32];
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_PENDING: AtomicU32 = AtomicU32::new(0);
+
 struct InterruptHandler<'a> {
-    id: u32,
     func: fn(),
     waiting: ProcessList<'a>,
+    // event flags posted to a process on each interrupt
+    notify: Option<(ProcessId, u32)>,
+    stats: Stats,
 }
 
 // TODO: remove dependency on nvic
-pub struct InterruptManager<'a> {
+pub struct InterruptManager<'a, const N: usize> {
     nvic: Nvic,
-    // TODO: variable length
-    handlers: [InterruptHandler<'a>; 10],
-    handler_count: usize,
+    handlers: [Option<InterruptHandler<'a>>; N],
+    // index of the handler for each IRQ
+    slots: [u8; IRQ_COUNT],
 }
 
-impl<'a> InterruptManager<'a> {
-    pub fn create(nvic: Nvic) -> InterruptManager<'a> {
-        unsafe {
-            InterruptManager {
-                nvic,
-                handlers: mem::uninitialized(),
-                handler_count: 0,
-            }
+impl<'a, const N: usize> InterruptManager<'a, N> {
+    pub fn create(nvic: Nvic) -> InterruptManager<'a, N> {
+        if N >= NO_HANDLER as usize {
+            panic!("limit exceed");
+        }
+        InterruptManager {
+            nvic,
+            handlers: [(); N].map(|_| None),
+            slots: [NO_HANDLER; IRQ_COUNT],
         }
     }
 
     pub fn register(&mut self, id: u32, func: fn()) {
-        if self.handler_count >= 10 {
-            panic!("limit exceed");
+        self.register_handler(id, func, None);
+    }
+
+    pub fn register_notify(&mut self, id: u32, func: fn(), process: ProcessId, flags: u32) {
+        self.register_handler(id, func, Some((process, flags)));
+    }
+
+    fn register_handler(&mut self, id: u32, func: fn(), notify: Option<(ProcessId, u32)>) {
+        if id as usize >= IRQ_COUNT || self.handler(id).is_some() {
+            panic!("invalid irq");
         }
+        let slot = match self.handlers.iter().position(|handler| handler.is_none()) {
+            Some(slot) => slot,
+            None => panic!("limit exceed"),
+        };
+        self.handlers[slot] = Some(InterruptHandler {
+            func,
+            waiting: ProcessList::new(),
+            notify,
+            stats: Stats::new(),
+        });
+        self.slots[id as usize] = slot as u8;
+        #[cfg(target_arch = "arm")]
         unsafe {
             IRQS[id as usize] = Vector {
                 handler: DefaultIrqHandler,
             };
         }
+        self.nvic.set_priority(id, IRQ_PRIORITY);
         self.nvic.enable(id);
-        self.handlers[self.handler_count] = InterruptHandler {
-            id,
-            func,
-            waiting: ProcessList::new(),
-        };
-        self.handler_count += 1;
     }
 
-    pub fn push_wait(&mut self, tar_id: u32, item: &'a mut ProcessListItem<'a>) {
-        for i in 0..self.handler_count {
-            let id = self.handlers[i].id;
-            if id == tar_id {
-                self.handlers[i].waiting.push(item);
-                return;
+    // the processes still waiting for the interrupt are given back
+    pub fn unregister(&mut self, id: u32) -> Option<ProcessList<'a>> {
+        self.handler(id)?;
+        self.nvic.disable(id);
+        self.nvic.clear_pending(id);
+        #[cfg(target_arch = "arm")]
+        unsafe {
+            IRQS[id as usize] = Vector { reserved: 0 };
+        }
+        PENDING_IRQS[id as usize / 32].fetch_and(!(1 << (id % 32)), Ordering::SeqCst);
+        let slot = self.slots[id as usize] as usize;
+        self.slots[id as usize] = NO_HANDLER;
+        self.handlers[slot].take().map(|handler| handler.waiting)
+    }
+
+    // lower values are higher priorities, the ones above IRQ_PRIORITY preempt the kernel
+    pub fn set_priority(&mut self, id: u32, priority: u8) {
+        self.nvic.set_priority(id, priority);
+    }
+
+    pub fn stats(&mut self, id: u32) -> Option<Stats> {
+        self.handler(id).map(|handler| handler.stats)
+    }
+
+    fn handler(&mut self, id: u32) -> Option<&mut InterruptHandler<'a>> {
+        let slot = *self.slots.get(id as usize)?;
+        self.handlers.get_mut(slot as usize)?.as_mut()
+    }
+
+    // the item is given back if no handler is registered for the IRQ
+    pub fn push_wait(
+        &mut self,
+        tar_id: u32,
+        item: &'a mut ProcessListItem<'a>,
+    ) -> Result<(), &'a mut ProcessListItem<'a>> {
+        match self.handler(tar_id) {
+            Some(handler) => {
+                handler.waiting.push(item);
+                Ok(())
             }
+            None => Err(item),
         }
-        panic!("no handler");
     }
 
-    pub fn check_pending(&mut self) -> ProcessList<'a> {
+    pub fn remove_wait(&mut self, id: &ProcessId) -> Option<&'a mut ProcessListItem<'a>> {
+        self.handlers
+            .iter_mut()
+            .flatten()
+            .find_map(|handler| handler.waiting.remove_by(|item| item == id))
+    }
+
+    // `notify` is called with the process and the flags registered by register_notify
+    pub fn check_pending<F>(&mut self, mut notify: F) -> ProcessList<'a>
+    where
+        F: FnMut(&ProcessId, u32),
+    {
         let mut process_list = ProcessList::new();
-        for i in 0..self.handler_count {
-            let id = self.handlers[i].id as u32;
-            if self.nvic.is_pending(id) {
-                (self.handlers[i].func)();
-                process_list.join(&mut self.handlers[i].waiting);
-                self.nvic.clear_pending(id);
-                self.nvic.enable(id);
+        for (word, pending) in PENDING_IRQS.iter().enumerate() {
+            if pending.load(Ordering::Relaxed) == 0 {
+                continue;
+            }
+            for id in pending_ids(word, pending.swap(0, Ordering::SeqCst)) {
+                let slot = self.slots[id as usize] as usize;
+                if let Some(Some(handler)) = self.handlers.get_mut(slot) {
+                    let start = cycle_count();
+                    (handler.func)();
+                    handler.stats.add(cycles_since(start));
+                    if let Some((process, flags)) = &handler.notify {
+                        notify(process, *flags);
+                    }
+                    process_list.join(&mut handler.waiting);
+                    self.nvic.clear_pending(id);
+                    self.nvic.enable(id);
+                }
             }
         }
         process_list
     }
 }
 
+// IRQ numbers of the bits set in a word of PENDING_IRQS
+fn pending_ids(word: usize, mut bits: u32) -> impl Iterator<Item = u32> {
+    core::iter::from_fn(move || {
+        if bits == 0 {
+            return None;
+        }
+        let bit = bits.trailing_zeros();
+        bits &= bits - 1;
+        Some(word as u32 * 32 + bit)
+    })
+}
+
+// mask the interrupt until the kernel handled it, mark it in PENDING_IRQS
+// and pend the switch to the kernel
+#[cfg(target_arch = "arm")]
 #[naked]
 pub unsafe extern "C" fn DefaultIrqHandler() {
     asm!(
-        "movw lr, #0xfff9",
-        "movt lr, #0xffff",
         "mrs r0, IPSR",
         "and r0, #0xff",
         "sub r0, #16",
@@ -91,7 +191,34 @@ pub unsafe extern "C" fn DefaultIrqHandler() {
         "mov r2, #0xe180",
         "movt r2, #0xe000",
         "str r0, [r2, r1, lsl #2]",
+        "ldr r2, =PENDING_IRQS",
+        "add r2, r2, r1, lsl #2",
+        "1:",
+        "ldrex r3, [r2]",
+        "orr r3, r3, r0",
+        "strex r1, r3, [r2]",
+        "cmp r1, #0",
+        "bne 1b",
+        "mov r0, #0x10000000",
+        "mov r2, #0xed04",
+        "movt r2, #0xe000",
+        "str r0, [r2, #0]",
         "bx lr",
         options(noreturn),
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_ids() {
+        let mut ids = pending_ids(1, (1 << 0) | (1 << 5) | (1 << 31));
+        assert_eq!(Some(32), ids.next());
+        assert_eq!(Some(37), ids.next());
+        assert_eq!(Some(63), ids.next());
+        assert_eq!(None, ids.next());
+        assert_eq!(None, pending_ids(0, 0).next());
+    }
+}
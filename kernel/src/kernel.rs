@@ -1,27 +1,52 @@
-use crate::interrupt_manager::InterruptManager;
+use crate::clock::{self, Clock};
+use crate::event_manager::{take_flags, EventManager, WaitMode};
+use crate::fault::FAULT_FIRED;
+use crate::interrupt_manager::{InterruptManager, IRQ_PRIORITY};
 use crate::message_manager::MessageManager;
-use crate::process::Process;
+use crate::name_registry::{Name, NameRegistry};
+use crate::process::{
+    BlockedOn, Process, CODE_REGION, FAULTED_EXIT_CODE, KILLED_EXIT_CODE, STACK_OVERFLOW_EXIT_CODE,
+};
+use crate::process_allocator::ProcessAllocator;
+use crate::process_list::{ProcessList, ProcessListItem};
 use crate::process_manager::{ProcessId, ProcessManager};
 use crate::scheduler::Scheduler;
+use crate::stats::{
+    cycle_count, cycles_since, Stats, IRQ_STATS, PROCESS_STATS, SYSCALL_COUNT, SYSCALL_STATS,
+};
+use crate::sync_manager::SyncManager;
+use crate::sys_error::{SysError, SUCCESS};
 use crate::syscall_id;
+use crate::timer_manager::{Expired, TimerManager, TimerMode};
+use arch::dwt::Dwt;
+#[cfg(fpu)]
+use arch::fpu::Fpu;
+use arch::interrupt;
+use arch::mpu::Mpu;
+use arch::scb::{Scb, SystemHandler};
 use arch::StackFrame;
 use core::cell::RefCell;
-use core::slice::from_raw_parts;
 use embedded_hal::serial::Write;
 use log::dhprintln;
 use rt::SYSCALL_FIRED;
 
-pub struct Kernel<'a, S, W> {
+pub struct Kernel<'a, S, W, const N: usize> {
     scheduler: RefCell<S>,
-    interrupt_manager: InterruptManager<'a>,
+    interrupt_manager: InterruptManager<'a, N>,
     serial: RefCell<W>,
     process_manager: ProcessManager<'a, Process<'a>>,
-    // process_manager: RefCell<ProcessManager<'a, Process<'a>>>,
     message_manager: RefCell<MessageManager<'a>>,
-    //message_manager: MessageManager<'a>,
+    timer_manager: TimerManager<'a>,
+    process_allocator: ProcessAllocator,
+    name_registry: NameRegistry<'a>,
+    sync_manager: SyncManager<'a>,
+    event_manager: EventManager<'a>,
+    syscall_stats: [Stats; SYSCALL_COUNT],
+    clock: Clock,
+    deep_sleep: bool,
 }
 
-impl<'a, S, W> Kernel<'a, S, W>
+impl<'a, S, W, const N: usize> Kernel<'a, S, W, N>
 where
     S: Scheduler<'a>,
     W: Write<char>,
@@ -29,127 +54,888 @@ where
     pub fn create(
         scheduler: S,
         serial: W,
-        interrupt_manager: InterruptManager<'a>,
+        interrupt_manager: InterruptManager<'a, N>,
         process_manager: ProcessManager<'a, Process<'a>>,
         message_manager: MessageManager<'a>,
-    ) -> Kernel<'a, S, W> {
+        timer_manager: TimerManager<'a>,
+        process_allocator: ProcessAllocator,
+        name_registry: NameRegistry<'a>,
+        clock: Clock,
+    ) -> Kernel<'a, S, W, N> {
         Kernel {
             scheduler: RefCell::new(scheduler),
             serial: RefCell::new(serial),
             interrupt_manager,
             process_manager,
-            //process_manager: RefCell::new(process_manager),
             message_manager: RefCell::new(message_manager),
-            //message_manager,
+            timer_manager,
+            process_allocator,
+            name_registry,
+            sync_manager: SyncManager::new(),
+            event_manager: EventManager::new(),
+            syscall_stats: [Stats::new(); SYSCALL_COUNT],
+            clock,
+            deep_sleep: false,
         }
     }
 
+    // idle in deep sleep when no process waits for the clock,
+    // SysTick may stop in it so another interrupt has to wake the kernel
+    pub fn set_deep_sleep(&mut self, enabled: bool) {
+        self.deep_sleep = enabled;
+    }
+
     pub fn run(&'a mut self) -> ! {
+        let scb = Scb::new();
+        // the switch back to the kernel waits until no other handler is active
+        scb.set_priority(SystemHandler::PendSV, 0xff);
+        scb.set_priority(SystemHandler::SysTick, IRQ_PRIORITY);
+        scb.enable_fault_handler(SystemHandler::BusFault);
+        scb.enable_fault_handler(SystemHandler::UsageFault);
+        unsafe { interrupt::set_basepri(IRQ_PRIORITY) };
+        Dwt::new().enable_cycle_counter();
+        #[cfg(fpu)]
+        Fpu::new().enable();
+        let mpu = Mpu::new();
+        mpu.set_region(CODE_REGION, &Process::code_region());
+        mpu.enable();
         unsafe {
-            asm!("cpsid i", options(nomem, nostack));
+            asm!("dsb", "isb", options(nomem, nostack));
         }
         let interrupt_manager = &mut self.interrupt_manager;
         let process_manager = &mut self.process_manager;
+        let timer_manager = &mut self.timer_manager;
+        let process_allocator = &mut self.process_allocator;
+        let name_registry = &mut self.name_registry;
+        let sync_manager = &mut self.sync_manager;
+        let event_manager = &mut self.event_manager;
+        let syscall_stats = &mut self.syscall_stats;
+        let clock = &self.clock;
+        let tick_hz = clock.tick_hz();
+        let deep_sleep = self.deep_sleep;
+        clock.start();
         loop {
             let mut sched = self.scheduler.borrow_mut();
             let mut serial = self.serial.borrow_mut();
             let mut message_manager = self.message_manager.borrow_mut();
-            //let mut process_manager = self.process_manager.borrow_mut();
             let current_id: Option<&mut ProcessId> = sched.get_current_proc();
             let mut should_schedule_next = false;
 
             match current_id {
                 Some(item) => {
                     let mut syscall: Option<*const u32> = None;
-                    process_manager.get_mut(item).map(|process| {
+                    let mut faulted = false;
+                    let mut overflowed = false;
+                    if let Some(process) = process_manager.get_mut(item) {
+                        // the process was woken before the timeout of its last wait
+                        if process.timeout {
+                            timer_manager.cancel_timeout(item);
+                            process.timeout = false;
+                        }
+                        let start = cycle_count();
                         process.execute();
+                        process.stats.add(cycles_since(start));
+                        // entering the process cleared the mask
+                        unsafe { interrupt::set_basepri(IRQ_PRIORITY) };
+                        overflowed = process.stack_overflowed();
                         unsafe {
-                            if SYSCALL_FIRED > 0 {
+                            if SYSCALL_FIRED > 0 && !overflowed {
                                 syscall.replace(process.sp as *const u32);
-                                SYSCALL_FIRED = 0;
+                            }
+                            SYSCALL_FIRED = 0;
+                            if FAULT_FIRED > 0 {
+                                faulted = true;
+                                FAULT_FIRED = 0;
                             }
                         }
-                    });
+                    }
 
                     match syscall {
                         Some(sp) => {
                             let base_frame = unsafe { StackFrame::from_ptr_mut(sp) };
                             let svc_id = base_frame.r0;
+                            let timeout = timeout_ticks(svc_id, base_frame);
+                            // failing syscalls overwrite it with a SysError
+                            base_frame.r0 = SUCCESS;
+                            let start = cycle_count();
                             match svc_id {
+                                // no timer is left for the timeout
+                                _ if timeout.is_some() && timer_manager.is_full() => {
+                                    base_frame.r0 = SysError::ResourceExhausted as u32;
+                                }
                                 syscall_id::PRINT => {
-                                    let arg2 = base_frame.r2 as usize;
-                                    let arg1 =
-                                        unsafe { from_raw_parts(base_frame.r1 as *const u8, arg2) };
-
-                                    for i in 0..arg2 {
-                                        let _ = serial.write(arg1[i] as char);
+                                    let current = process_manager.get(item).unwrap();
+                                    match current.user_slice(base_frame.r1, base_frame.r2) {
+                                        Some(message) => {
+                                            for c in message {
+                                                let _ = serial.write(*c as char);
+                                            }
+                                        }
+                                        None => {
+                                            base_frame.r0 = SysError::InvalidAddress as u32;
+                                        }
                                     }
                                 }
                                 syscall_id::YIELD => {
                                     should_schedule_next = true;
                                 }
-                                syscall_id::WAIT_IRQ => {
-                                    let arg1 = base_frame.r1;
-                                    interrupt_manager
-                                        .push_wait(arg1, sched.pop_current_proc().unwrap());
+                                syscall_id::WAIT_IRQ | syscall_id::WAIT_IRQ_TIMEOUT => {
+                                    let id = item.clone();
+                                    let current = sched.pop_current_proc().unwrap();
+                                    match interrupt_manager.push_wait(base_frame.r1, current) {
+                                        Ok(()) => {
+                                            block(
+                                                &id,
+                                                BlockedOn::Interrupt,
+                                                timeout,
+                                                timer_manager,
+                                                process_manager,
+                                            );
+                                        }
+                                        Err(current) => {
+                                            sched.push(current);
+                                            base_frame.r0 = SysError::InvalidArgument as u32;
+                                        }
+                                    }
                                 }
                                 syscall_id::WAIT_SYSTICK => {
                                     let current = sched.pop_current_proc().unwrap();
                                     sched.push_wait(current);
                                 }
+                                syscall_id::SLEEP | syscall_id::SLEEP_UNTIL => {
+                                    let ticks = clock::ticks();
+                                    let deadline = if svc_id == syscall_id::SLEEP {
+                                        ticks + base_frame.r1 as u64
+                                    } else {
+                                        ((base_frame.r2 as u64) << 32) | base_frame.r1 as u64
+                                    };
+                                    if deadline > ticks {
+                                        if timer_manager.is_full() {
+                                            base_frame.r0 = SysError::ResourceExhausted as u32;
+                                        } else {
+                                            let current = sched.pop_current_proc().unwrap();
+                                            timer_manager.sleep_until(deadline, current);
+                                        }
+                                    }
+                                }
                                 syscall_id::DORMANT => {
                                     sched.pop_current_proc().unwrap();
                                 }
-                                syscall_id::SEND_MESSAGE => {
-                                    let arg1 = base_frame.r1;
-                                    let arg2 = base_frame.r2;
-                                    let target = process_manager.borrow_mut(&ProcessId(arg1));
-                                    if target.is_none() {
-                                        base_frame.r0 = 0;
+                                syscall_id::EXIT => {
+                                    let id = item.clone();
+                                    sched.pop_current_proc().unwrap();
+                                    release_resources(
+                                        &id,
+                                        &mut *sched,
+                                        process_manager,
+                                        &mut message_manager,
+                                        name_registry,
+                                        sync_manager,
+                                        timer_manager,
+                                    );
+                                    terminate(
+                                        &id,
+                                        base_frame.r1,
+                                        &mut *sched,
+                                        process_manager,
+                                        process_allocator,
+                                    );
+                                }
+                                syscall_id::KILL => {
+                                    let id = ProcessId(base_frame.r1);
+                                    if process_manager.get(&id).is_none() {
+                                        base_frame.r0 = SysError::NoSuchProcess as u32;
                                     } else {
-                                        let result =
-                                            message_manager.send_message(target.unwrap(), arg2);
-                                        base_frame.r0 = result.clone() as u32;
+                                        // the item of a killed process is not used again
+                                        let _ = sched
+                                            .remove(&id)
+                                            .or_else(|| timer_manager.cancel_wakeup(&id))
+                                            .or_else(|| {
+                                                take_blocked(
+                                                    &id,
+                                                    interrupt_manager,
+                                                    process_manager,
+                                                    &mut message_manager,
+                                                    name_registry,
+                                                    sync_manager,
+                                                    event_manager,
+                                                )
+                                            });
+                                        release_resources(
+                                            &id,
+                                            &mut *sched,
+                                            process_manager,
+                                            &mut message_manager,
+                                            name_registry,
+                                            sync_manager,
+                                            timer_manager,
+                                        );
+                                        terminate(
+                                            &id,
+                                            KILLED_EXIT_CODE,
+                                            &mut *sched,
+                                            process_manager,
+                                            process_allocator,
+                                        );
+                                    }
+                                }
+                                syscall_id::WAIT | syscall_id::WAIT_TIMEOUT => {
+                                    let current_id = item.clone();
+                                    let id = ProcessId(base_frame.r1);
+                                    match process_manager.borrow_mut(&id) {
+                                        Some(target) if id != current_id => {
+                                            let current = sched.pop_current_proc().unwrap();
+                                            target.exit_waiting.push(current);
+                                            block(
+                                                &current_id,
+                                                BlockedOn::Process(id.clone()),
+                                                timeout,
+                                                timer_manager,
+                                                process_manager,
+                                            );
+                                        }
+                                        Some(_) => {
+                                            base_frame.r0 = SysError::InvalidArgument as u32;
+                                        }
+                                        None => match process_manager.take_exit_code(&id) {
+                                            Some(exit_code) => {
+                                                base_frame.r1 = exit_code;
+                                            }
+                                            None => {
+                                                base_frame.r0 = SysError::NoSuchProcess as u32;
+                                            }
+                                        },
+                                    }
+                                }
+                                syscall_id::SPAWN => {
+                                    let parent = process_manager.borrow(item).unwrap();
+                                    match process_allocator.allocate(
+                                        base_frame.r1,
+                                        base_frame.r2 as usize,
+                                        base_frame.r3,
+                                        base_frame.r12 as usize,
+                                    ) {
+                                        Ok((node, list_item)) => {
+                                            // the child inherits the shared regions
+                                            for region in parent.shared_regions() {
+                                                node.item.1.add_region(*region);
+                                            }
+                                            let id = process_manager.register(node);
+                                            *list_item.item = id.clone();
+                                            sched.push(list_item);
+                                            base_frame.r1 = id.0;
+                                        }
+                                        Err(error) => {
+                                            base_frame.r0 = SysError::from(error) as u32;
+                                        }
+                                    }
+                                }
+                                syscall_id::SEND_MESSAGE => {
+                                    let result = send_message(
+                                        &ProcessId(base_frame.r1),
+                                        base_frame.r2,
+                                        &mut *sched,
+                                        process_manager,
+                                        &mut message_manager,
+                                    );
+                                    if let Err(error) = result {
+                                        base_frame.r0 = error as u32;
                                     }
                                 }
                                 syscall_id::RECEIVE_MESSAGE => {
                                     let result = message_manager
                                         .receive_message(process_manager.borrow_mut(item).unwrap());
-                                    if result.is_none() {
-                                        base_frame.r0 = 0;
+                                    match result {
+                                        Some(message) => {
+                                            base_frame.r1 = message;
+                                        }
+                                        None => {
+                                            base_frame.r0 = SysError::WouldBlock as u32;
+                                        }
+                                    }
+                                }
+                                syscall_id::WAIT_MESSAGE | syscall_id::WAIT_MESSAGE_TIMEOUT => {
+                                    let result = message_manager
+                                        .receive_message(process_manager.borrow_mut(item).unwrap());
+                                    if let Some(message) = result {
+                                        base_frame.r1 = message;
+                                    } else {
+                                        let id = item.clone();
+                                        let current = sched.pop_current_proc().unwrap();
+                                        message_manager.wait_message(current);
+                                        block(
+                                            &id,
+                                            BlockedOn::Message,
+                                            timeout,
+                                            timer_manager,
+                                            process_manager,
+                                        );
+                                    }
+                                }
+                                syscall_id::SEND_BUF | syscall_id::SEND_BUF_TIMEOUT => {
+                                    let current_id = item.clone();
+                                    let target_id = ProcessId(base_frame.r1);
+                                    let current = process_manager.borrow_mut(&current_id).unwrap();
+                                    let target = process_manager.borrow_mut(&target_id);
+                                    let checked = message_manager.check_buf(
+                                        current,
+                                        base_frame.r2,
+                                        base_frame.r3,
+                                    );
+                                    match target {
+                                        None => {
+                                            base_frame.r0 = SysError::NoSuchProcess as u32;
+                                        }
+                                        Some(_) if target_id == current_id => {
+                                            base_frame.r0 = SysError::InvalidArgument as u32;
+                                        }
+                                        Some(_) if checked.is_err() => {
+                                            base_frame.r0 = checked.unwrap_err() as u32;
+                                        }
+                                        Some(target) => {
+                                            match message_manager.take_buf_receiver(&target_id) {
+                                                Some(receiver) => {
+                                                    match message_manager.copy_buf(current, target)
+                                                    {
+                                                        Some(len) => {
+                                                            let frame = target.frame_mut();
+                                                            frame.r0 = SUCCESS;
+                                                            frame.r1 = current_id.0;
+                                                            frame.r2 = len;
+                                                            sched.push(receiver);
+                                                        }
+                                                        None => {
+                                                            message_manager.wait_buf(receiver);
+                                                            base_frame.r0 =
+                                                                SysError::InvalidArgument as u32;
+                                                        }
+                                                    }
+                                                }
+                                                None => {
+                                                    let current = sched.pop_current_proc().unwrap();
+                                                    target.buf_senders.push(current);
+                                                    block(
+                                                        &current_id,
+                                                        BlockedOn::Process(target_id.clone()),
+                                                        timeout,
+                                                        timer_manager,
+                                                        process_manager,
+                                                    );
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                                syscall_id::RECV_BUF | syscall_id::RECV_BUF_TIMEOUT => {
+                                    let current_id = item.clone();
+                                    let current = process_manager.borrow_mut(&current_id).unwrap();
+                                    if !current.can_write(base_frame.r1, base_frame.r2) {
+                                        base_frame.r0 = SysError::InvalidAddress as u32;
+                                    } else {
+                                        // senders whose buffer does not fit are failed
+                                        let mut resumed = ProcessList::new();
+                                        let mut received = false;
+                                        while let Some(sender_item) = current.buf_senders.pop() {
+                                            let sender = process_manager
+                                                .borrow_mut(&sender_item.item)
+                                                .unwrap();
+                                            match message_manager.copy_buf(sender, current) {
+                                                Some(len) => {
+                                                    sender.frame_mut().r0 = SUCCESS;
+                                                    base_frame.r1 = sender_item.item.0;
+                                                    base_frame.r2 = len;
+                                                    resumed.push(sender_item);
+                                                    received = true;
+                                                    break;
+                                                }
+                                                None => {
+                                                    sender.frame_mut().r0 =
+                                                        SysError::InvalidArgument as u32;
+                                                    resumed.push(sender_item);
+                                                }
+                                            }
+                                        }
+                                        if !received {
+                                            let current = sched.pop_current_proc().unwrap();
+                                            message_manager.wait_buf(current);
+                                            block(
+                                                &current_id,
+                                                BlockedOn::Buf,
+                                                timeout,
+                                                timer_manager,
+                                                process_manager,
+                                            );
+                                        }
+                                        sched.resume_list(&mut resumed);
+                                    }
+                                }
+                                syscall_id::CALL | syscall_id::CALL_TIMEOUT => {
+                                    let current_id = item.clone();
+                                    let target_id = ProcessId(base_frame.r1);
+                                    let target = process_manager.borrow_mut(&target_id);
+                                    match target {
+                                        Some(target) if target_id != current_id => {
+                                            let current = sched.pop_current_proc().unwrap();
+                                            match message_manager.take_call_receiver(&target_id) {
+                                                Some(server) => {
+                                                    let frame = target.frame_mut();
+                                                    frame.r0 = SUCCESS;
+                                                    frame.r1 = current_id.0;
+                                                    frame.r2 = base_frame.r2;
+                                                    target.reply_waiting.push(current);
+                                                    sched.push(server);
+                                                }
+                                                None => {
+                                                    target.callers.push(current);
+                                                }
+                                            }
+                                            block(
+                                                &current_id,
+                                                BlockedOn::Process(target_id.clone()),
+                                                timeout,
+                                                timer_manager,
+                                                process_manager,
+                                            );
+                                        }
+                                        Some(_) => {
+                                            base_frame.r0 = SysError::InvalidArgument as u32;
+                                        }
+                                        None => {
+                                            base_frame.r0 = SysError::NoSuchProcess as u32;
+                                        }
+                                    }
+                                }
+                                syscall_id::RECEIVE_CALL | syscall_id::RECEIVE_CALL_TIMEOUT => {
+                                    let current_id = item.clone();
+                                    let current = process_manager.borrow_mut(&current_id).unwrap();
+                                    match current.callers.pop() {
+                                        Some(client) => {
+                                            let request = process_manager
+                                                .get_mut(&client.item)
+                                                .unwrap()
+                                                .frame_mut()
+                                                .r2;
+                                            base_frame.r1 = client.item.0;
+                                            base_frame.r2 = request;
+                                            current.reply_waiting.push(client);
+                                        }
+                                        None => {
+                                            let current = sched.pop_current_proc().unwrap();
+                                            message_manager.wait_call(current);
+                                            block(
+                                                &current_id,
+                                                BlockedOn::Call,
+                                                timeout,
+                                                timer_manager,
+                                                process_manager,
+                                            );
+                                        }
+                                    }
+                                }
+                                syscall_id::REPLY => {
+                                    let client_id = ProcessId(base_frame.r1);
+                                    let current = process_manager.borrow_mut(item).unwrap();
+                                    match current.reply_waiting.remove_by(|id| *id == client_id) {
+                                        Some(client) => {
+                                            let frame = process_manager
+                                                .get_mut(&client_id)
+                                                .unwrap()
+                                                .frame_mut();
+                                            frame.r0 = SUCCESS;
+                                            frame.r1 = base_frame.r2;
+                                            sched.push(client);
+                                        }
+                                        None => {
+                                            base_frame.r0 = SysError::InvalidArgument as u32;
+                                        }
+                                    }
+                                }
+                                syscall_id::REGISTER_NAME => {
+                                    let current_id = item.clone();
+                                    let current = process_manager.get(&current_id).unwrap();
+                                    let registered = read_name(
+                                        current,
+                                        base_frame.r1,
+                                        base_frame.r2,
+                                    )
+                                    .and_then(|name| {
+                                        if name_registry.lookup(&name).is_some() {
+                                            Err(SysError::AlreadyExists)
+                                        } else if name_registry.register(name, current_id.clone()) {
+                                            Ok(())
+                                        } else {
+                                            Err(SysError::ResourceExhausted)
+                                        }
+                                    });
+                                    if let Err(error) = registered {
+                                        base_frame.r0 = error as u32;
                                     } else {
-                                        base_frame.r0 = 1;
-                                        base_frame.r1 = result.unwrap().clone();
+                                        let mut waiting = name_registry.take_waiting();
+                                        while let Some(waiting_item) = waiting.pop() {
+                                            let process = process_manager
+                                                .get_mut(&waiting_item.item)
+                                                .unwrap();
+                                            let (addr, len) = {
+                                                let frame = process.frame_mut();
+                                                (frame.r1, frame.r2)
+                                            };
+                                            let found = read_name(process, addr, len)
+                                                .ok()
+                                                .and_then(|name| name_registry.lookup(&name));
+                                            match found {
+                                                Some(id) => {
+                                                    let frame = process.frame_mut();
+                                                    frame.r0 = SUCCESS;
+                                                    frame.r1 = id.0;
+                                                    sched.push(waiting_item);
+                                                }
+                                                None => {
+                                                    name_registry.wait(waiting_item);
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                                syscall_id::LOOKUP | syscall_id::LOOKUP_TIMEOUT => {
+                                    let current_id = item.clone();
+                                    let current = process_manager.get(&current_id).unwrap();
+                                    match read_name(current, base_frame.r1, base_frame.r2) {
+                                        Ok(name) => match name_registry.lookup(&name) {
+                                            Some(id) => {
+                                                base_frame.r1 = id.0;
+                                            }
+                                            None => {
+                                                let current = sched.pop_current_proc().unwrap();
+                                                name_registry.wait(current);
+                                                block(
+                                                    &current_id,
+                                                    BlockedOn::Name,
+                                                    timeout,
+                                                    timer_manager,
+                                                    process_manager,
+                                                );
+                                            }
+                                        },
+                                        Err(error) => {
+                                            base_frame.r0 = error as u32;
+                                        }
+                                    }
+                                }
+                                syscall_id::MUTEX_CREATE => match sync_manager.create_mutex() {
+                                    Some(handle) => base_frame.r1 = handle,
+                                    None => base_frame.r0 = SysError::ResourceExhausted as u32,
+                                },
+                                syscall_id::MUTEX_LOCK | syscall_id::MUTEX_LOCK_TIMEOUT => {
+                                    let current_id = item.clone();
+                                    match sync_manager.lock(base_frame.r1, &current_id) {
+                                        Ok(true) => {}
+                                        Ok(false) => {
+                                            let current = sched.pop_current_proc().unwrap();
+                                            sync_manager.push_wait(base_frame.r1, current);
+                                            block(
+                                                &current_id,
+                                                BlockedOn::Sync,
+                                                timeout,
+                                                timer_manager,
+                                                process_manager,
+                                            );
+                                        }
+                                        Err(_) => {
+                                            base_frame.r0 = SysError::InvalidArgument as u32;
+                                        }
+                                    }
+                                }
+                                syscall_id::MUTEX_UNLOCK => {
+                                    match sync_manager.unlock(base_frame.r1, item) {
+                                        Ok(Some(next)) => {
+                                            process_manager
+                                                .get_mut(&next.item)
+                                                .unwrap()
+                                                .frame_mut()
+                                                .r0 = SUCCESS;
+                                            sched.push(next);
+                                        }
+                                        Ok(None) => {}
+                                        Err(_) => {
+                                            base_frame.r0 = SysError::InvalidArgument as u32;
+                                        }
+                                    }
+                                }
+                                syscall_id::SEMAPHORE_CREATE => {
+                                    match sync_manager.create_semaphore(base_frame.r1) {
+                                        Some(handle) => base_frame.r1 = handle,
+                                        None => base_frame.r0 = SysError::ResourceExhausted as u32,
+                                    }
+                                }
+                                syscall_id::SEMAPHORE_WAIT | syscall_id::SEMAPHORE_WAIT_TIMEOUT => {
+                                    let current_id = item.clone();
+                                    match sync_manager.wait(base_frame.r1) {
+                                        Ok(true) => {}
+                                        Ok(false) => {
+                                            let current = sched.pop_current_proc().unwrap();
+                                            sync_manager.push_wait(base_frame.r1, current);
+                                            block(
+                                                &current_id,
+                                                BlockedOn::Sync,
+                                                timeout,
+                                                timer_manager,
+                                                process_manager,
+                                            );
+                                        }
+                                        Err(_) => {
+                                            base_frame.r0 = SysError::InvalidArgument as u32;
+                                        }
+                                    }
+                                }
+                                syscall_id::SEMAPHORE_SIGNAL => {
+                                    match sync_manager.signal(base_frame.r1) {
+                                        Ok(Some(next)) => {
+                                            process_manager
+                                                .get_mut(&next.item)
+                                                .unwrap()
+                                                .frame_mut()
+                                                .r0 = SUCCESS;
+                                            sched.push(next);
+                                        }
+                                        Ok(None) => {}
+                                        Err(_) => {
+                                            base_frame.r0 = SysError::InvalidArgument as u32;
+                                        }
+                                    }
+                                }
+                                syscall_id::NOTIFY => {
+                                    let target_id = ProcessId(base_frame.r1);
+                                    let target = process_manager.borrow_mut(&target_id);
+                                    match target {
+                                        Some(target) => {
+                                            if let Some(item) = event_manager.notify(
+                                                &target_id,
+                                                target,
+                                                base_frame.r2,
+                                            ) {
+                                                sched.push(item);
+                                            }
+                                        }
+                                        None => {
+                                            base_frame.r0 = SysError::NoSuchProcess as u32;
+                                        }
+                                    }
+                                }
+                                syscall_id::WAIT_FLAGS | syscall_id::WAIT_FLAGS_TIMEOUT => {
+                                    let current_id = item.clone();
+                                    let mask = base_frame.r1;
+                                    let mode = WaitMode::from_u32(base_frame.r2);
+                                    let current = process_manager.get_mut(&current_id).unwrap();
+                                    match take_flags(&mut current.event_flags, mask, mode) {
+                                        Some(flags) => {
+                                            base_frame.r1 = flags;
+                                        }
+                                        None if mask == 0 => {
+                                            base_frame.r0 = SysError::InvalidArgument as u32;
+                                        }
+                                        None => {
+                                            let current = sched.pop_current_proc().unwrap();
+                                            event_manager.wait(current);
+                                            block(
+                                                &current_id,
+                                                BlockedOn::Event,
+                                                timeout,
+                                                timer_manager,
+                                                process_manager,
+                                            );
+                                        }
+                                    }
+                                }
+                                syscall_id::STATS => {
+                                    let id = base_frame.r2;
+                                    let stats = match base_frame.r1 {
+                                        PROCESS_STATS => process_manager
+                                            .get(&ProcessId(id))
+                                            .map(|process| process.stats)
+                                            .ok_or(SysError::NoSuchProcess),
+                                        SYSCALL_STATS => syscall_stats
+                                            .get(id as usize)
+                                            .copied()
+                                            .ok_or(SysError::InvalidArgument),
+                                        IRQ_STATS => interrupt_manager
+                                            .stats(id)
+                                            .ok_or(SysError::InvalidArgument),
+                                        _ => Err(SysError::InvalidArgument),
+                                    };
+                                    match stats {
+                                        Ok(stats) => {
+                                            base_frame.r1 = stats.count;
+                                            base_frame.r2 = stats.cycles as u32;
+                                            base_frame.r3 = (stats.cycles >> 32) as u32;
+                                        }
+                                        Err(error) => {
+                                            base_frame.r0 = error as u32;
+                                        }
+                                    }
+                                }
+                                syscall_id::NOW => {
+                                    let ticks = clock::ticks();
+                                    base_frame.r1 = ticks as u32;
+                                    base_frame.r2 = (ticks >> 32) as u32;
+                                    base_frame.r3 = tick_hz;
+                                }
+                                syscall_id::TIMER_CREATE => {
+                                    let ticks = base_frame.r1 as u64;
+                                    let target_id = ProcessId(base_frame.r3);
+                                    if ticks == 0 {
+                                        base_frame.r0 = SysError::InvalidArgument as u32;
+                                    } else if process_manager.get(&target_id).is_none() {
+                                        base_frame.r0 = SysError::NoSuchProcess as u32;
+                                    } else if timer_manager.is_full() {
+                                        base_frame.r0 = SysError::ResourceExhausted as u32;
+                                    } else {
+                                        let period = match TimerMode::from_u32(base_frame.r2) {
+                                            TimerMode::OneShot => None,
+                                            TimerMode::Periodic => Some(ticks),
+                                        };
+                                        base_frame.r1 = timer_manager.create_timer(
+                                            item,
+                                            &target_id,
+                                            base_frame.r12,
+                                            clock::ticks() + ticks,
+                                            period,
+                                        );
+                                    }
+                                }
+                                syscall_id::TIMER_CANCEL => {
+                                    if timer_manager.cancel_timer(base_frame.r1, item).is_err() {
+                                        base_frame.r0 = SysError::InvalidArgument as u32;
                                     }
                                 }
                                 _ => {
-                                    // TODO: error handling
-                                    panic!("unknown svc");
+                                    base_frame.r0 = SysError::InvalidSyscall as u32;
                                 }
                             }
+                            if let Some(stats) = syscall_stats.get_mut(svc_id as usize) {
+                                stats.add(cycles_since(start));
+                            }
+                        }
+                        None if faulted || overflowed => {
+                            let id = item.clone();
+                            if faulted {
+                                let status = scb.take_fault_status();
+                                // the frame is not reliable if the fault happened while stacking
+                                let pc = process_manager
+                                    .get_mut(&id)
+                                    .filter(|p| p.contains(p.sp as u32, 0x20))
+                                    .map(|p| p.frame_mut().return_addr);
+                                dhprintln!(
+                                    "process {} faulted at {:x?}: {} (cfsr {:x}, hfsr {:x}, mmfar {:x?}, bfar {:x?})",
+                                    id.0,
+                                    pc,
+                                    status.name(),
+                                    status.cfsr,
+                                    status.hfsr,
+                                    status.mmfar,
+                                    status.bfar
+                                );
+                            }
+                            let exit_code = if overflowed {
+                                dhprintln!("process {} overflowed its stack", id.0);
+                                STACK_OVERFLOW_EXIT_CODE
+                            } else {
+                                FAULTED_EXIT_CODE
+                            };
+                            sched.pop_current_proc().unwrap();
+                            release_resources(
+                                &id,
+                                &mut *sched,
+                                process_manager,
+                                &mut message_manager,
+                                name_registry,
+                                sync_manager,
+                                timer_manager,
+                            );
+                            terminate(
+                                &id,
+                                exit_code,
+                                &mut *sched,
+                                process_manager,
+                                process_allocator,
+                            );
                         }
                         None => {}
                     }
                 }
                 None => {
-                    dhprintln!("sleeping");
-                    unsafe {
-                        asm!(
-                            "cpsie i",
-                            "wfi",
-                            "cpsid i",
-                        );
-                    }
+                    // sleep through the ticks until the next deadline
+                    let deadline = timer_manager.next_deadline();
+                    let idle_ticks = if sched.has_waiting() {
+                        1
+                    } else {
+                        deadline
+                            .map_or(u64::MAX, |deadline| deadline.saturating_sub(clock::ticks()))
+                    };
+                    let deep = deep_sleep && idle_ticks == u64::MAX;
+                    // PRIMASK keeps the handlers from running before wfi
+                    // and until the clock counted the idle ticks
+                    interrupt::free(|| {
+                        let idle = clock.suspend(idle_ticks);
+                        scb.set_sleepdeep(deep);
+                        unsafe {
+                            interrupt::set_basepri(0);
+                            asm!("wfi");
+                        }
+                        scb.set_sleepdeep(false);
+                        clock.resume(idle);
+                    });
+                    unsafe { interrupt::set_basepri(IRQ_PRIORITY) };
                 }
             }
 
-            let mut released_list = interrupt_manager.check_pending();
+            let mut notified_list = ProcessList::new();
+            let mut released_list = interrupt_manager.check_pending(|id, flags| {
+                let notified = process_manager
+                    .borrow_mut(id)
+                    .and_then(|process| event_manager.notify(id, process, flags));
+                if let Some(item) = notified {
+                    notified_list.push(item);
+                }
+            });
             sched.resume_list(&mut released_list);
+            sched.resume_list(&mut notified_list);
 
-            if unsafe { SHOULD_DISPATCH } > 0 {
+            let elapsed = clock::ticks() - timer_manager.get_ticks();
+            if elapsed > 0 {
+                let mut expired_list = timer_manager.tick(elapsed, |expired| match expired {
+                    // a message which does not fit in the message pool is dropped
+                    Expired::Message(target_id, message) => {
+                        let _ = send_message(
+                            &target_id,
+                            message,
+                            &mut *sched,
+                            process_manager,
+                            &mut message_manager,
+                        );
+                    }
+                    // it is no longer waiting if it was woken in the meantime
+                    Expired::Timeout(id) => {
+                        let removed = take_blocked(
+                            &id,
+                            interrupt_manager,
+                            process_manager,
+                            &mut message_manager,
+                            name_registry,
+                            sync_manager,
+                            event_manager,
+                        );
+                        if let Some(process) = process_manager.get_mut(&id) {
+                            process.timeout = false;
+                            if let Some(item) = removed {
+                                process.frame_mut().r0 = SysError::TimedOut as u32;
+                                sched.push(item);
+                            }
+                        }
+                    }
+                });
+                sched.resume_list(&mut expired_list);
                 sched.resume_waiting();
                 sched.schedule_next();
-                unsafe { SHOULD_DISPATCH = 0 };
             } else if should_schedule_next {
                 sched.schedule_next();
             }
@@ -157,17 +943,200 @@ where
     }
 }
 
-#[no_mangle]
-pub static mut SHOULD_DISPATCH: u32 = 0;
+// give back the kernel objects held by a terminating process
+fn release_resources<'a, S>(
+    id: &ProcessId,
+    sched: &mut S,
+    process_manager: &mut ProcessManager<'a, Process<'a>>,
+    message_manager: &mut MessageManager<'a>,
+    name_registry: &mut NameRegistry<'a>,
+    sync_manager: &mut SyncManager<'a>,
+    timer_manager: &mut TimerManager<'a>,
+) where
+    S: Scheduler<'a>,
+{
+    if let Some(process) = process_manager.get_mut(id) {
+        message_manager.release(process);
+    }
+    name_registry.unregister(id);
+    timer_manager.release(id);
+    let mut owners = sync_manager.release(id);
+    for owner_id in owners.iter() {
+        if let Some(owner) = process_manager.get_mut(owner_id) {
+            owner.frame_mut().r0 = SUCCESS;
+        }
+    }
+    sched.resume_list(&mut owners);
+}
+
+// queue the message and hand it over if the target waits for one
+fn send_message<'a, S>(
+    target_id: &ProcessId,
+    message: u32,
+    sched: &mut S,
+    process_manager: &mut ProcessManager<'a, Process<'a>>,
+    message_manager: &mut MessageManager<'a>,
+) -> Result<(), SysError>
+where
+    S: Scheduler<'a>,
+{
+    let target = process_manager
+        .borrow_mut(target_id)
+        .ok_or(SysError::NoSuchProcess)?;
+    if !message_manager.send_message(target, message) {
+        return Err(SysError::ResourceExhausted);
+    }
+    if let Some(receiver) = message_manager.take_receiver(target_id) {
+        let target = process_manager.borrow_mut(target_id).unwrap();
+        let message = message_manager.receive_message(target).unwrap();
+        let frame = process_manager.get_mut(target_id).unwrap().frame_mut();
+        frame.r0 = SUCCESS;
+        frame.r1 = message;
+        sched.push(receiver);
+    }
+    Ok(())
+}
+
+// wake up the processes waiting for the exit and free the process, the exit code is kept for a
+// later wait if nobody waited
+fn terminate<'a, S>(
+    id: &ProcessId,
+    exit_code: u32,
+    sched: &mut S,
+    process_manager: &mut ProcessManager<'a, Process<'a>>,
+    process_allocator: &mut ProcessAllocator,
+) where
+    S: Scheduler<'a>,
+{
+    let process = match process_manager.borrow_mut(id) {
+        Some(process) => process,
+        None => return,
+    };
+    abort_waiting(&mut process.buf_senders, sched, process_manager);
+    abort_waiting(&mut process.callers, sched, process_manager);
+    abort_waiting(&mut process.reply_waiting, sched, process_manager);
+
+    if process.exit_waiting.is_empty() {
+        process_manager.add_exit_code(id.clone(), exit_code);
+    } else {
+        for waiting_id in process.exit_waiting.iter() {
+            if let Some(waiting) = process_manager.get_mut(waiting_id) {
+                let frame = waiting.frame_mut();
+                frame.r0 = SUCCESS;
+                frame.r1 = exit_code;
+            }
+        }
+        sched.resume_list(&mut process.exit_waiting);
+    }
+    if let Some(node) = process_manager.remove(id) {
+        process_allocator.release(node);
+    }
+}
+
+// the ticks of a syscall with a timeout, read before the result overwrites the registers
+fn timeout_ticks(svc_id: u32, frame: &StackFrame) -> Option<u32> {
+    match svc_id {
+        syscall_id::WAIT_MESSAGE_TIMEOUT | syscall_id::RECEIVE_CALL_TIMEOUT => Some(frame.r1),
+        syscall_id::WAIT_IRQ_TIMEOUT
+        | syscall_id::MUTEX_LOCK_TIMEOUT
+        | syscall_id::SEMAPHORE_WAIT_TIMEOUT
+        | syscall_id::WAIT_TIMEOUT => Some(frame.r2),
+        syscall_id::LOOKUP_TIMEOUT
+        | syscall_id::WAIT_FLAGS_TIMEOUT
+        | syscall_id::CALL_TIMEOUT
+        | syscall_id::RECV_BUF_TIMEOUT => Some(frame.r3),
+        syscall_id::SEND_BUF_TIMEOUT => Some(frame.r12),
+        _ => None,
+    }
+}
+
+// record the list the process is blocked in,
+// it fails with TimedOut unless it is woken up before the deadline
+fn block<'a>(
+    id: &ProcessId,
+    blocked_on: BlockedOn,
+    ticks: Option<u32>,
+    timer_manager: &mut TimerManager<'a>,
+    process_manager: &mut ProcessManager<'a, Process<'a>>,
+) {
+    let process = process_manager.get_mut(id).unwrap();
+    process.blocked_on = Some(blocked_on);
+    if let Some(ticks) = ticks {
+        timer_manager.set_timeout(clock::ticks() + ticks as u64, id);
+        process.timeout = true;
+    }
+}
+
+// take the process out of the list it is blocked in, it is neither ready nor sleeping
+fn take_blocked<'a, const N: usize>(
+    id: &ProcessId,
+    interrupt_manager: &mut InterruptManager<'a, N>,
+    process_manager: &mut ProcessManager<'a, Process<'a>>,
+    message_manager: &mut MessageManager<'a>,
+    name_registry: &mut NameRegistry<'a>,
+    sync_manager: &mut SyncManager<'a>,
+    event_manager: &mut EventManager<'a>,
+) -> Option<&'a mut ProcessListItem<'a>> {
+    match process_manager.get_mut(id)?.blocked_on.take()? {
+        BlockedOn::Interrupt => interrupt_manager.remove_wait(id),
+        BlockedOn::Message => message_manager.take_receiver(id),
+        BlockedOn::Buf => message_manager.take_buf_receiver(id),
+        BlockedOn::Call => message_manager.take_call_receiver(id),
+        BlockedOn::Name => name_registry.remove_waiting(id),
+        BlockedOn::Sync => sync_manager.remove_wait(id),
+        BlockedOn::Event => event_manager.remove_wait(id),
+        BlockedOn::Process(owner) => process_manager.get_mut(&owner)?.remove_waiting(id),
+    }
+}
+
+fn read_name(process: &Process, addr: u32, len: u32) -> Result<Name, SysError> {
+    let name = process
+        .user_slice(addr, len)
+        .ok_or(SysError::InvalidAddress)?;
+    Name::new(name).ok_or(SysError::InvalidArgument)
+}
+
+// resume the processes with a failure result
+fn abort_waiting<'a, S>(
+    process_list: &mut ProcessList<'a>,
+    sched: &mut S,
+    process_manager: &mut ProcessManager<'a, Process<'a>>,
+) where
+    S: Scheduler<'a>,
+{
+    for id in process_list.iter() {
+        if let Some(process) = process_manager.get_mut(id) {
+            process.frame_mut().r0 = SysError::Aborted as u32;
+        }
+    }
+    sched.resume_list(process_list);
+}
 
 #[no_mangle]
 pub unsafe extern "C" fn SysTick() {
+    clock::tick();
+    Scb::new().set_pendsv();
+}
+
+// handlers only pend PendSV, a process interrupted by them returns to the kernel here
+/// # Safety
+/// Only the processor calls it, after a handler pended PendSV.
+#[no_mangle]
+#[naked]
+pub unsafe extern "C" fn PendSV() {
     asm!(
+        "tst lr, #4",
+        "beq 1f",
+        "ldr r0, =PROCESS_EXC_RETURN",
+        "str lr, [r0, #0]",
+        "mrs r0, CONTROL",
+        "bic r0, r0, #1",
+        "msr CONTROL, r0",
+        "isb",
         "movw lr, #0xfff9",
         "movt lr, #0xffff",
-        "ldr r0, =SHOULD_DISPATCH",
-        "mov r1, #1",
-        "str r1, [r0, #0]",
-        options(nostack),
+        "1:",
+        "bx lr",
+        options(noreturn),
     );
 }
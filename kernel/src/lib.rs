@@ -3,12 +3,23 @@
 #![feature(asm)]
 #![feature(naked_functions)]
 
+pub mod clock;
+pub mod event_manager;
+#[cfg(target_arch = "arm")]
+pub mod fault;
 pub mod interrupt_manager;
+#[cfg(target_arch = "arm")]
 pub mod kernel;
 pub mod macros;
 pub mod message_manager;
+pub mod name_registry;
 pub mod process;
+pub mod process_allocator;
 pub mod process_list;
 pub mod process_manager;
 pub mod scheduler;
+pub mod stats;
+pub mod sync_manager;
+pub mod sys_error;
 pub mod syscall_id;
+pub mod timer_manager;
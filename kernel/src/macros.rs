@@ -1,10 +1,13 @@
 #[macro_export]
 macro_rules! stack_allocate {
     ($n:expr) => {{
+        // aligned to its size to be mapped by a single MPU region
+        #[repr(C, align($n))]
+        struct Stack([u8; $n]);
         #[link_section = ".uninit"]
-        static mut STACK: [u8; $n] = [0; $n];
+        static mut STACK: Stack = Stack([0; $n]);
 
-        unsafe { &STACK[0] as *const u8 as u32 + $n }
+        unsafe { &STACK.0[0] as *const u8 as u32 + $n }
     }};
 }
 
@@ -23,7 +26,7 @@ macro_rules! process_create {
         let entry = $entry as u32;
         let sp = $crate::stack_allocate!($n);
         let regs = $crate::reg_allocate!();
-        Process::create(entry, sp, regs)
+        Process::create(entry, sp - $n, $n, regs)
     }};
 }
 
@@ -32,14 +35,33 @@ macro_rules! process_register {
     ($scheduler:expr,$process_manager:expr,$process:expr) => {
         let mut node = util::avl_tree::Node::new($crate::process_manager::ProcessId(0), $process);
         let id = $process_manager.register(&mut node);
-        let mut item = $crate::process_list::ProcessListItem::create(id);
+        let entry = $crate::process_list::ProcessEntry::new(id);
+        let mut item = $crate::process_list::ProcessListItem::create(entry);
+        $scheduler.push(&mut item);
+    };
+    ($scheduler:expr,$process_manager:expr,$process:expr,priority = $priority:expr) => {
+        let mut node = util::avl_tree::Node::new($crate::process_manager::ProcessId(0), $process);
+        let id = $process_manager.register(&mut node);
+        let entry = $crate::process_list::ProcessEntry::with_priority(id, $priority)
+            .expect("invalid priority");
+        let mut item = $crate::process_list::ProcessListItem::create(entry);
+        $scheduler.push(&mut item);
+    };
+    ($scheduler:expr,$process_manager:expr,$process:expr,$id:ident,priority = $priority:expr) => {
+        let mut node = util::avl_tree::Node::new($crate::process_manager::ProcessId(0), $process);
+        let id = $process_manager.register(&mut node);
+        let $id = id.0;
+        let entry = $crate::process_list::ProcessEntry::with_priority(id, $priority)
+            .expect("invalid priority");
+        let mut item = $crate::process_list::ProcessListItem::create(entry);
         $scheduler.push(&mut item);
     };
     ($scheduler:expr,$process_manager:expr,$process:expr,$id:ident) => {
         let mut node = util::avl_tree::Node::new($crate::process_manager::ProcessId(0), $process);
         let id = $process_manager.register(&mut node);
         let $id = id.0;
-        let mut item = $crate::process_list::ProcessListItem::create(id);
+        let entry = $crate::process_list::ProcessEntry::new(id);
+        let mut item = $crate::process_list::ProcessListItem::create(entry);
         $scheduler.push(&mut item);
     };
 }
@@ -1,18 +1,34 @@
 use crate::process::Process;
+use crate::process_list::{ProcessList, ProcessListItem};
+use crate::process_manager::ProcessId;
+use crate::sys_error::SysError;
 use util::linked_list::{LinkedList, ListItem};
 
 pub struct MessageManager<'a> {
     buff: LinkedList<'a, u32>,
+    // processes blocked until a message arrives
+    receiving: ProcessList<'a>,
+    // processes blocked in recv_buf
+    buf_receiving: ProcessList<'a>,
+    // servers blocked until a call arrives
+    call_receiving: ProcessList<'a>,
+    max_buf_size: u32,
 }
 
 impl<'a> MessageManager<'a> {
-    pub fn new(buff: &'a mut [ListItem<'a, u32>]) -> MessageManager<'a> {
+    pub fn new(buff: &'a mut [ListItem<'a, u32>], max_buf_size: u32) -> MessageManager<'a> {
         let mut list = LinkedList::new();
         for item in buff.iter_mut() {
             list.push(item);
         }
 
-        MessageManager { buff: list }
+        MessageManager {
+            buff: list,
+            receiving: ProcessList::new(),
+            buf_receiving: ProcessList::new(),
+            call_receiving: ProcessList::new(),
+            max_buf_size,
+        }
     }
 
     pub fn send_message(&mut self, process: &'a mut Process<'a>, message: u32) -> bool {
@@ -27,6 +43,63 @@ impl<'a> MessageManager<'a> {
         true
     }
 
+    pub fn wait_message(&mut self, process: &'a mut ProcessListItem<'a>) {
+        self.receiving.push(process);
+    }
+
+    pub fn take_receiver(&mut self, id: &ProcessId) -> Option<&'a mut ProcessListItem<'a>> {
+        self.receiving.remove_by(|item| item == id)
+    }
+
+    pub fn check_buf(&self, process: &Process<'a>, addr: u32, len: u32) -> Result<(), SysError> {
+        if len > self.max_buf_size {
+            Err(SysError::InvalidArgument)
+        } else if !process.can_read(addr, len) {
+            Err(SysError::InvalidAddress)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn wait_buf(&mut self, process: &'a mut ProcessListItem<'a>) {
+        self.buf_receiving.push(process);
+    }
+
+    pub fn take_buf_receiver(&mut self, id: &ProcessId) -> Option<&'a mut ProcessListItem<'a>> {
+        self.buf_receiving.remove_by(|item| item == id)
+    }
+
+    pub fn wait_call(&mut self, process: &'a mut ProcessListItem<'a>) {
+        self.call_receiving.push(process);
+    }
+
+    pub fn take_call_receiver(&mut self, id: &ProcessId) -> Option<&'a mut ProcessListItem<'a>> {
+        self.call_receiving.remove_by(|item| item == id)
+    }
+
+    // copy the buffer passed to send_buf into the one passed to recv_buf
+    // and return the length, or None if it does not fit
+    pub fn copy_buf(&self, sender: &mut Process<'a>, receiver: &mut Process<'a>) -> Option<u32> {
+        let (src, len) = {
+            let frame = sender.frame_mut();
+            (frame.r2, frame.r3)
+        };
+        let (dst, capacity) = {
+            let frame = receiver.frame_mut();
+            (frame.r1, frame.r2)
+        };
+        if len > capacity {
+            return None;
+        }
+        let src = sender.user_slice(src, len)?;
+        receiver.user_slice_mut(dst, len)?.copy_from_slice(src);
+        Some(len)
+    }
+
+    pub fn release(&mut self, process: &mut Process<'a>) {
+        self.buff.join(&mut process.message_queue);
+    }
+
     pub fn receive_message(&mut self, process: &'a mut Process<'a>) -> Option<u32> {
         if process.message_queue.is_empty() {
             return None;
@@ -0,0 +1,154 @@
+use crate::process_list::{ProcessList, ProcessListItem};
+use crate::process_manager::ProcessId;
+use core::mem::size_of;
+use core::ptr;
+use util::avl_tree::{AvlTree, Node};
+use util::binary_tree::BinaryTree;
+
+pub const MAX_NAME_LEN: usize = 16;
+const MAX_NAMES: usize = 32;
+
+#[derive(PartialOrd, PartialEq, Eq, Ord, Clone)]
+pub struct Name {
+    bytes: [u8; MAX_NAME_LEN],
+    len: usize,
+}
+
+impl Name {
+    pub fn new(name: &[u8]) -> Option<Name> {
+        if name.is_empty() || name.len() > MAX_NAME_LEN {
+            return None;
+        }
+        let mut bytes = [0; MAX_NAME_LEN];
+        bytes[..name.len()].copy_from_slice(name);
+        Some(Name {
+            bytes,
+            len: name.len(),
+        })
+    }
+}
+
+pub struct NameRegistry<'a> {
+    map: AvlTree<'a, Name, ProcessId>,
+    nodes: *mut Node<'a, Name, ProcessId>,
+    len: usize,
+    // bitmap of the nodes in the map
+    used: u32,
+    // processes blocked in lookup
+    waiting: ProcessList<'a>,
+}
+
+impl<'a> NameRegistry<'a> {
+    pub fn new(buff: &'a mut [Node<'a, Name, ProcessId>]) -> NameRegistry<'a> {
+        if buff.len() > MAX_NAMES {
+            panic!("limit exceed");
+        }
+        NameRegistry {
+            map: AvlTree::new(),
+            nodes: buff.as_mut_ptr(),
+            len: buff.len(),
+            used: 0,
+            waiting: ProcessList::new(),
+        }
+    }
+
+    // fails if the name is already taken or there is no room
+    pub fn register(&mut self, name: Name, id: ProcessId) -> bool {
+        if self.map.get(&name).is_some() {
+            return false;
+        }
+        let index = (!self.used).trailing_zeros() as usize;
+        if index >= self.len {
+            return false;
+        }
+        self.used |= 1 << index;
+        let node = unsafe {
+            let node = self.nodes.add(index);
+            ptr::write(node, Node::new(name, id));
+            &mut *node
+        };
+        self.map.insert(node);
+        true
+    }
+
+    pub fn lookup(&self, name: &Name) -> Option<ProcessId> {
+        self.map.get(name).cloned()
+    }
+
+    // remove all names registered by the process
+    pub fn unregister(&mut self, id: &ProcessId) {
+        loop {
+            let name = match self.map.iter().find(|(_, owner)| owner == id) {
+                Some((name, _)) => name.clone(),
+                None => return,
+            };
+            let node = self.map.remove(&name).unwrap();
+            let index = (node as *mut _ as usize - self.nodes as usize)
+                / size_of::<Node<Name, ProcessId>>();
+            self.used &= !(1 << index);
+        }
+    }
+
+    pub fn wait(&mut self, process: &'a mut ProcessListItem<'a>) {
+        self.waiting.push(process);
+    }
+
+    pub fn take_waiting(&mut self) -> ProcessList<'a> {
+        let mut waiting = ProcessList::new();
+        waiting.join(&mut self.waiting);
+        waiting
+    }
+
+    pub fn remove_waiting(&mut self, id: &ProcessId) -> Option<&'a mut ProcessListItem<'a>> {
+        self.waiting.remove_by(|item| item == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem::MaybeUninit;
+
+    fn name(name: &str) -> Name {
+        Name::new(name.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_register() {
+        let mut buff: [Node<Name, ProcessId>; 2] = unsafe { MaybeUninit::uninit().assume_init() };
+        let mut registry = NameRegistry::new(&mut buff);
+        assert!(registry.register(name("led"), ProcessId(1)));
+        assert!(!registry.register(name("led"), ProcessId(2)));
+        assert!(registry.register(name("serial"), ProcessId(2)));
+        assert!(!registry.register(name("net"), ProcessId(3)));
+
+        assert_eq!(1, registry.lookup(&name("led")).unwrap().0);
+        assert_eq!(2, registry.lookup(&name("serial")).unwrap().0);
+        assert!(registry.lookup(&name("net")).is_none());
+    }
+
+    #[test]
+    fn test_unregister() {
+        let mut buff: [Node<Name, ProcessId>; 3] = unsafe { MaybeUninit::uninit().assume_init() };
+        let mut registry = NameRegistry::new(&mut buff);
+        assert!(registry.register(name("led"), ProcessId(1)));
+        assert!(registry.register(name("button"), ProcessId(2)));
+        assert!(registry.register(name("blink"), ProcessId(1)));
+
+        registry.unregister(&ProcessId(1));
+        assert!(registry.lookup(&name("led")).is_none());
+        assert!(registry.lookup(&name("blink")).is_none());
+        assert_eq!(2, registry.lookup(&name("button")).unwrap().0);
+
+        assert!(registry.register(name("led"), ProcessId(3)));
+        assert!(registry.register(name("blink"), ProcessId(3)));
+        assert_eq!(3, registry.lookup(&name("led")).unwrap().0);
+    }
+
+    #[test]
+    fn test_invalid_name() {
+        assert!(Name::new(b"").is_none());
+        assert!(Name::new(&[b'a'; MAX_NAME_LEN + 1]).is_none());
+        assert!(Name::new(&[b'a'; MAX_NAME_LEN]).is_some());
+    }
+}
@@ -1,6 +1,23 @@
-use core::slice::from_raw_parts_mut;
+use crate::process_list::{ProcessList, ProcessListItem};
+use crate::process_manager::ProcessId;
+use crate::stats::Stats;
+use arch::mpu::{AccessPermission, MemoryType, Mpu, Region};
+use arch::StackFrame;
+#[cfg(fpu)]
+use arch::{is_extended_frame, EXC_RETURN_PROCESS};
+use core::slice::{from_raw_parts, from_raw_parts_mut};
 use util::linked_list::LinkedList;
 
+pub const KILLED_EXIT_CODE: u32 = 0xffff_ffff;
+pub const FAULTED_EXIT_CODE: u32 = 0xffff_fffe;
+pub const STACK_OVERFLOW_EXIT_CODE: u32 = 0xffff_fffd;
+// painted at the bottom of the stack and checked on every switch
+const STACK_CANARY: u32 = 0xdead_beef;
+const CANARY_WORDS: usize = 4;
+// MPU region 0 maps the code for all processes, the rest is per process
+pub const CODE_REGION: u32 = 0;
+pub const PROCESS_REGIONS: usize = 7;
+
 #[derive(PartialEq)]
 pub enum ProcessState {
     READY,
@@ -9,19 +26,81 @@ pub enum ProcessState {
     DORMANT,
 }
 
+// the wait list of a blocked process, a timeout or a kill only searches that list
+#[derive(Clone, PartialEq)]
+pub enum BlockedOn {
+    Interrupt,
+    Message,
+    Buf,
+    Call,
+    Name,
+    Sync,
+    Event,
+    // exit_waiting, buf_senders, callers or reply_waiting of the process
+    Process(ProcessId),
+}
+
 pub struct Process<'a> {
     pub sp: *mut u8,
     pub regs: &'a mut [u32; 8],
+    pub stack_base: u32,
+    pub stack_size: u32,
     pub state: ProcessState,
     pub message_queue: LinkedList<'a, u32>,
+    pub exit_waiting: ProcessList<'a>,
+    // processes blocked in send_buf to this process
+    pub buf_senders: ProcessList<'a>,
+    // clients blocked in call until this process receives the request
+    pub callers: ProcessList<'a>,
+    // clients whose request was received and which wait for the reply
+    pub reply_waiting: ProcessList<'a>,
+    pub event_flags: u32,
+    // the stack region followed by the shared regions
+    pub regions: [Option<Region>; PROCESS_REGIONS],
+    // how often the process was dispatched and how long it ran
+    pub stats: Stats,
+    // a timer may still time out the last blocking syscall
+    pub timeout: bool,
+    // set when the process blocks, it is not cleared when the process is woken up
+    pub blocked_on: Option<BlockedOn>,
+    #[cfg(fpu)]
+    pub fp_context: FpContext,
+}
+
+// the FPU registers which are not part of the exception frame
+#[cfg(fpu)]
+#[repr(C)]
+pub struct FpContext {
+    pub s: [u32; 16],
+    // EXC_RETURN of the process, asm_execute_process reads it after s
+    pub exc_return: u32,
+}
+
+#[cfg(fpu)]
+impl FpContext {
+    pub const fn new() -> FpContext {
+        FpContext {
+            s: [0; 16],
+            exc_return: EXC_RETURN_PROCESS,
+        }
+    }
 }
 
 extern "C" {
+    static _sflash: u8;
+    static _eflash: u8;
+    static _srodata: u8;
+    static _erodata: u8;
+    #[cfg(not(fpu))]
     fn asm_execute_process(sp: *mut u8, regs: &mut [u32; 8]) -> *mut u8;
+    #[cfg(fpu)]
+    fn asm_execute_process(sp: *mut u8, regs: &mut [u32; 8], fp_context: &mut FpContext)
+        -> *mut u8;
 }
 
 impl<'a> Process<'a> {
-    pub fn create(entry: u32, sp: u32, regs: &'a mut [u32; 8]) -> Process {
+    pub fn create(entry: u32, stack_base: u32, stack_size: u32, regs: &'a mut [u32; 8]) -> Process {
+        let sp = stack_base + stack_size;
         let base_frame_ptr = (sp - 0x20) as *mut u32;
         let base_frame = unsafe { from_raw_parts_mut(base_frame_ptr, 8) };
         base_frame[0] = 0; // r0
@@ -32,15 +111,155 @@ impl<'a> Process<'a> {
         base_frame[5] = 0; // lr(r14)
         base_frame[6] = entry & !1; // return address
         base_frame[7] = 0x01000000; // xpsr, set thumb state
+        let canary = unsafe { from_raw_parts_mut(stack_base as *mut u32, CANARY_WORDS) };
+        canary.fill(STACK_CANARY);
+        let mut regions = [None; PROCESS_REGIONS];
+        regions[0] = Region::new(
+            stack_base,
+            stack_size,
+            AccessPermission::FullAccess,
+            MemoryType::Normal,
+            false,
+        );
         Process {
             sp: base_frame_ptr as *mut u8,
             regs: regs,
+            stack_base,
+            stack_size,
             state: ProcessState::DORMANT,
             message_queue: LinkedList::new(),
+            exit_waiting: ProcessList::new(),
+            buf_senders: ProcessList::new(),
+            callers: ProcessList::new(),
+            reply_waiting: ProcessList::new(),
+            event_flags: 0,
+            regions,
+            stats: Stats::new(),
+            timeout: false,
+            blocked_on: None,
+            #[cfg(fpu)]
+            fp_context: FpContext::new(),
+        }
+    }
+
+    // returns false if all the region slots are in use
+    pub fn add_region(&mut self, region: Region) -> bool {
+        match self.regions[1..].iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                slot.replace(region);
+                true
+            }
+            None => false,
         }
     }
 
+    pub fn shared_regions(&self) -> impl Iterator<Item = &Region> {
+        self.regions[1..].iter().flatten()
+    }
+
     pub fn execute(&mut self) {
-        self.sp = unsafe { asm_execute_process(self.sp, self.regs) };
+        let mpu = Mpu::new();
+        for (i, region) in self.regions.iter().enumerate() {
+            let number = CODE_REGION + 1 + i as u32;
+            match region {
+                Some(region) => mpu.set_region(number, region),
+                None => mpu.clear_region(number),
+            }
+        }
+        #[cfg(not(fpu))]
+        {
+            self.sp = unsafe { asm_execute_process(self.sp, self.regs) };
+        }
+        #[cfg(fpu)]
+        {
+            self.sp = unsafe { asm_execute_process(self.sp, self.regs, &mut self.fp_context) };
+        }
+    }
+
+    // the process used the FPU and its frame is an ExtendedStackFrame
+    #[cfg(fpu)]
+    pub fn uses_fpu(&self) -> bool {
+        is_extended_frame(self.fp_context.exc_return)
+    }
+
+    pub fn stack_overflowed(&self) -> bool {
+        let canary = unsafe { from_raw_parts(self.stack_base as *const u32, CANARY_WORDS) };
+        (self.sp as u32) < self.stack_base + (CANARY_WORDS * 4) as u32
+            || canary.iter().any(|word| *word != STACK_CANARY)
+    }
+
+    // remove the process from the lists of processes blocked on this one
+    pub fn remove_waiting(&mut self, id: &ProcessId) -> Option<&'a mut ProcessListItem<'a>> {
+        let exit_waiting = &mut self.exit_waiting;
+        let buf_senders = &mut self.buf_senders;
+        let callers = &mut self.callers;
+        let reply_waiting = &mut self.reply_waiting;
+        exit_waiting
+            .remove_by(|item| item == id)
+            .or_else(|| buf_senders.remove_by(|item| item == id))
+            .or_else(|| callers.remove_by(|item| item == id))
+            .or_else(|| reply_waiting.remove_by(|item| item == id))
+    }
+
+    pub fn contains(&self, addr: u32, len: u32) -> bool {
+        let stack_end = self.stack_base + self.stack_size;
+        addr.checked_add(len)
+            .map_or(false, |end| addr >= self.stack_base && end <= stack_end)
+    }
+
+    // the stack and the readable regions, plus read-only data such as string literals
+    pub fn can_read(&self, addr: u32, len: u32) -> bool {
+        let (rodata_start, rodata_end) =
+            unsafe { (&_srodata as *const u8 as u32, &_erodata as *const u8 as u32) };
+        self.contains(addr, len)
+            || self
+                .shared_regions()
+                .any(|region| region.is_readable() && region.contains(addr, len))
+            || addr
+                .checked_add(len)
+                .map_or(false, |end| addr >= rodata_start && end <= rodata_end)
+    }
+
+    pub fn can_write(&self, addr: u32, len: u32) -> bool {
+        self.contains(addr, len)
+            || self
+                .shared_regions()
+                .any(|region| region.is_writable() && region.contains(addr, len))
+    }
+
+    // memory passed to a syscall, None if the process cannot read it
+    pub fn user_slice(&self, addr: u32, len: u32) -> Option<&[u8]> {
+        if self.can_read(addr, len) {
+            Some(unsafe { from_raw_parts(addr as *const u8, len as usize) })
+        } else {
+            None
+        }
+    }
+
+    pub fn user_slice_mut(&mut self, addr: u32, len: u32) -> Option<&mut [u8]> {
+        if self.can_write(addr, len) {
+            Some(unsafe { from_raw_parts_mut(addr as *mut u8, len as usize) })
+        } else {
+            None
+        }
+    }
+
+    // flash is mapped read-only and executable
+    pub fn code_region() -> Region {
+        let (flash_start, flash_end) =
+            unsafe { (&_sflash as *const u8 as u32, &_eflash as *const u8 as u32) };
+        Region::covering(
+            flash_start,
+            flash_end,
+            AccessPermission::ReadOnly,
+            MemoryType::Normal,
+            true,
+        )
+        .unwrap()
+    }
+
+    // the exception frame saved when the process was stopped
+    pub fn frame_mut(&mut self) -> &mut StackFrame {
+        unsafe { StackFrame::from_ptr_mut(self.sp as *const u32) }
     }
 }
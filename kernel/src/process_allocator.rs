@@ -0,0 +1,128 @@
+use crate::process::Process;
+use crate::process_list::{ProcessEntry, ProcessListItem};
+use crate::process_manager::ProcessId;
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr;
+use util::allocator::LockedAllocator;
+use util::avl_tree::Node;
+
+const MIN_STACK_SIZE: usize = 256;
+// size classes of the kernel heap, large enough for process stacks
+const HEAP_SIZE_CLASSES: usize = 12;
+
+#[derive(Debug, PartialEq)]
+pub enum SpawnError {
+    NoMemory = 1,
+    NoProcessSlot = 2,
+    InvalidPriority = 3,
+}
+
+// kernel objects of a spawned process, allocated as one block
+#[repr(C)]
+struct ProcessBlock<'a> {
+    node: Node<'a, ProcessId, Process<'a>>,
+    item: ProcessListItem<'a>,
+    regs: [u32; 8],
+}
+
+pub struct ProcessAllocator {
+    allocator: LockedAllocator<HEAP_SIZE_CLASSES>,
+    heap_start: usize,
+    heap_end: usize,
+    max_processes: usize,
+    count: usize,
+}
+
+impl ProcessAllocator {
+    pub fn new(heap: &mut [u8], max_processes: usize) -> ProcessAllocator {
+        let heap_start = heap.as_mut_ptr() as usize;
+        let heap_end = heap_start + heap.len();
+        let allocator = LockedAllocator::with_size_classes();
+        allocator.add_area(heap_start, heap_end);
+        ProcessAllocator {
+            allocator,
+            heap_start,
+            heap_end,
+            max_processes,
+            count: 0,
+        }
+    }
+
+    // the returned node and list item must be registered with the id of the process
+    pub fn allocate<'a>(
+        &mut self,
+        entry: u32,
+        stack_size: usize,
+        arg: u32,
+        priority: usize,
+    ) -> Result<
+        (
+            &'a mut Node<'a, ProcessId, Process<'a>>,
+            &'a mut ProcessListItem<'a>,
+        ),
+        SpawnError,
+    > {
+        let list_entry = ProcessEntry::with_priority(ProcessId(0), priority)
+            .map_err(|_| SpawnError::InvalidPriority)?;
+        if self.count >= self.max_processes {
+            return Err(SpawnError::NoProcessSlot);
+        }
+        let stack_layout = Self::stack_layout(stack_size).ok_or(SpawnError::NoMemory)?;
+        let block_layout = Self::block_layout();
+        let stack = unsafe { self.allocator.alloc(stack_layout) };
+        if stack.is_null() {
+            return Err(SpawnError::NoMemory);
+        }
+        let block = unsafe { self.allocator.alloc(block_layout) } as *mut ProcessBlock<'a>;
+        if block.is_null() {
+            unsafe { self.allocator.dealloc(stack, stack_layout) };
+            return Err(SpawnError::NoMemory);
+        }
+        self.count += 1;
+
+        let block = unsafe {
+            ptr::write(&mut (*block).regs, [0; 8]);
+            &mut *block
+        };
+        let mut process = Process::create(
+            entry,
+            stack as u32,
+            stack_layout.size() as u32,
+            &mut block.regs,
+        );
+        process.frame_mut().r0 = arg;
+        unsafe {
+            ptr::write(&mut block.node, Node::new(ProcessId(0), process));
+            ptr::write(&mut block.item, ProcessListItem::create(list_entry));
+        }
+        Ok((&mut block.node, &mut block.item))
+    }
+
+    // processes which were not spawned are ignored
+    pub fn release<'a>(&mut self, node: &'a mut Node<'a, ProcessId, Process<'a>>) {
+        let block = node as *mut _ as *mut ProcessBlock<'a>;
+        if (block as usize) < self.heap_start || (block as usize) >= self.heap_end {
+            return;
+        }
+        let process = &node.item.1;
+        let stack_layout = Self::stack_layout(process.stack_size as usize).unwrap();
+        unsafe {
+            self.allocator
+                .dealloc(process.stack_base as *mut u8, stack_layout);
+            self.allocator
+                .dealloc(block as *mut u8, Self::block_layout());
+        }
+        self.count -= 1;
+    }
+
+    // stacks are aligned to their power of two size to be mapped by a single MPU region
+    fn stack_layout(stack_size: usize) -> Option<Layout> {
+        let size = stack_size.max(MIN_STACK_SIZE).checked_next_power_of_two()?;
+        Layout::from_size_align(size, size).ok()
+    }
+
+    fn block_layout() -> Layout {
+        let layout = Layout::new::<ProcessBlock>();
+        Layout::from_size_align(layout.size().next_power_of_two(), layout.align()).unwrap()
+    }
+}
@@ -1,5 +1,59 @@
 use crate::process_manager::ProcessId;
+use core::ops::{Deref, DerefMut};
 use util::linked_list::{LinkedList, ListItem};
 
-pub type ProcessListItem<'a> = ListItem<'a, ProcessId>;
-pub type ProcessList<'a> = LinkedList<'a, ProcessId>;
+// priority 0 is the highest, only the priority scheduler orders by it
+pub const PRIORITY_LEVELS: usize = 32;
+pub const DEFAULT_PRIORITY: usize = PRIORITY_LEVELS - 1;
+
+// the list item of a process carries its priority between the scheduler and the waiting lists
+pub struct ProcessEntry {
+    id: ProcessId,
+    priority: u8,
+}
+
+impl ProcessEntry {
+    pub const fn new(id: ProcessId) -> ProcessEntry {
+        ProcessEntry {
+            id,
+            priority: DEFAULT_PRIORITY as u8,
+        }
+    }
+
+    pub fn with_priority(id: ProcessId, priority: usize) -> Result<ProcessEntry, ()> {
+        if priority >= PRIORITY_LEVELS {
+            return Err(());
+        }
+        Ok(ProcessEntry {
+            id,
+            priority: priority as u8,
+        })
+    }
+
+    pub fn priority(&self) -> usize {
+        self.priority as usize
+    }
+}
+
+impl Deref for ProcessEntry {
+    type Target = ProcessId;
+
+    fn deref(&self) -> &Self::Target {
+        &self.id
+    }
+}
+
+impl DerefMut for ProcessEntry {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.id
+    }
+}
+
+impl PartialEq<ProcessId> for ProcessEntry {
+    fn eq(&self, other: &ProcessId) -> bool {
+        self.id == *other
+    }
+}
+
+pub type ProcessListItem<'a> = ListItem<'a, ProcessEntry>;
+pub type ProcessList<'a> = LinkedList<'a, ProcessEntry>;
@@ -1,12 +1,18 @@
-use util::avl_tree::{AvlTree, Node};
+use util::avl_tree::{AvlTree, IterMut, Node};
 use util::binary_tree::BinaryTree;
 
 #[derive(PartialOrd, PartialEq, Eq, Ord, Clone)]
 pub struct ProcessId(pub u32);
 
+// exit codes kept for wait after the processes were removed, the oldest one is dropped first
+const EXIT_CODES_LEN: usize = 8;
+const NO_EXIT_CODE: Option<(ProcessId, u32)> = None;
+
 pub struct ProcessManager<'a, P> {
     map: AvlTree<'a, ProcessId, P>,
     count: u32,
+    exit_codes: [Option<(ProcessId, u32)>; EXIT_CODES_LEN],
+    next_exit_code: usize,
 }
 
 impl<'a, P> ProcessManager<'a, P> {
@@ -14,6 +20,8 @@ impl<'a, P> ProcessManager<'a, P> {
         ProcessManager {
             map: AvlTree::new(),
             count: 0,
+            exit_codes: [NO_EXIT_CODE; EXIT_CODES_LEN],
+            next_exit_code: 0,
         }
     }
 
@@ -40,4 +48,47 @@ impl<'a, P> ProcessManager<'a, P> {
     pub fn borrow_mut(&mut self, id: &ProcessId) -> Option<&'a mut P> {
         self.map.borrow_mut(id)
     }
+
+    pub fn remove(&mut self, id: &ProcessId) -> Option<&'a mut Node<'a, ProcessId, P>> {
+        self.map.remove(id)
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'a, ProcessId, P> {
+        self.map.iter_mut()
+    }
+
+    pub fn add_exit_code(&mut self, id: ProcessId, exit_code: u32) {
+        self.exit_codes[self.next_exit_code] = Some((id, exit_code));
+        self.next_exit_code = (self.next_exit_code + 1) % EXIT_CODES_LEN;
+    }
+
+    // an exit code is only returned once
+    pub fn take_exit_code(&mut self, id: &ProcessId) -> Option<u32> {
+        self.exit_codes
+            .iter_mut()
+            .find(|slot| slot.as_ref().map_or(false, |(exited, _)| exited == id))
+            .and_then(|slot| slot.take())
+            .map(|(_, exit_code)| exit_code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes() {
+        let mut process_manager: ProcessManager<u32> = ProcessManager::new();
+        for i in 0..EXIT_CODES_LEN as u32 + 1 {
+            process_manager.add_exit_code(ProcessId(i), i + 10);
+        }
+        // the oldest one was dropped
+        assert_eq!(None, process_manager.take_exit_code(&ProcessId(0)));
+        assert_eq!(Some(11), process_manager.take_exit_code(&ProcessId(1)));
+        assert_eq!(None, process_manager.take_exit_code(&ProcessId(1)));
+        assert_eq!(
+            Some(EXIT_CODES_LEN as u32 + 10),
+            process_manager.take_exit_code(&ProcessId(EXIT_CODES_LEN as u32))
+        );
+    }
 }
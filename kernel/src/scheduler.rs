@@ -1,6 +1,7 @@
 use crate::process_list::{ProcessList, ProcessListItem};
 use crate::process_manager::ProcessId;
 
+pub mod priority_scheduler;
 pub mod simple_scheduler;
 
 pub trait Scheduler<'a> {
@@ -11,6 +12,8 @@ pub trait Scheduler<'a> {
     fn push(&mut self, proc: &'a mut ProcessListItem<'a>);
     fn push_wait(&mut self, proc: &'a mut ProcessListItem<'a>);
     fn resume_waiting(&mut self);
+    fn has_waiting(&self) -> bool;
+    fn remove(&mut self, id: &ProcessId) -> Option<&'a mut ProcessListItem<'a>>;
 }
 
 pub enum ExecResult {
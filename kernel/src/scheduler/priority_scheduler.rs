@@ -0,0 +1,233 @@
+use core::ops::DerefMut;
+
+use super::Scheduler;
+use crate::process_list::{ProcessList, ProcessListItem, PRIORITY_LEVELS};
+use crate::process_manager::ProcessId;
+
+// the priority is taken from the list item of each process
+pub struct PriorityScheduler<'a> {
+    ready: [ProcessList<'a>; PRIORITY_LEVELS],
+    ready_bitmap: u32,
+    waiting: ProcessList<'a>,
+}
+
+impl<'a> Scheduler<'a> for PriorityScheduler<'a> {
+    fn get_current_proc(&mut self) -> Option<&mut ProcessId> {
+        let priority = self.highest_priority()?;
+        self.ready[priority]
+            .head_mut()
+            .map(|item| (*item).deref_mut().deref_mut())
+    }
+
+    fn pop_current_proc(&mut self) -> Option<&'a mut ProcessListItem<'a>> {
+        let priority = self.highest_priority()?;
+        let item = self.ready[priority].pop();
+        if self.ready[priority].is_empty() {
+            self.ready_bitmap &= !(1 << priority);
+        }
+        item
+    }
+
+    fn schedule_next(&mut self) {
+        if let Some(priority) = self.highest_priority() {
+            let current = self.ready[priority].pop().unwrap();
+            self.ready[priority].push(current);
+        }
+    }
+
+    fn resume_list(&mut self, process_list: &mut ProcessList<'a>) {
+        while !process_list.is_empty() {
+            let item = process_list.pop().unwrap();
+            self.push(item);
+        }
+    }
+
+    fn push(&mut self, proc: &'a mut ProcessListItem<'a>) {
+        let priority = proc.priority();
+        self.ready[priority].push(proc);
+        self.ready_bitmap |= 1 << priority;
+    }
+
+    fn push_wait(&mut self, proc: &'a mut ProcessListItem<'a>) {
+        self.waiting.push(proc);
+    }
+
+    fn resume_waiting(&mut self) {
+        let mut waiting = ProcessList::new();
+        waiting.join(&mut self.waiting);
+        self.resume_list(&mut waiting);
+    }
+
+    fn has_waiting(&self) -> bool {
+        !self.waiting.is_empty()
+    }
+
+    fn remove(&mut self, id: &ProcessId) -> Option<&'a mut ProcessListItem<'a>> {
+        let mut bitmap = self.ready_bitmap;
+        while bitmap != 0 {
+            let priority = bitmap.trailing_zeros() as usize;
+            bitmap &= bitmap - 1;
+            let result = self.ready[priority].remove_by(|item| item == id);
+            if result.is_some() {
+                if self.ready[priority].is_empty() {
+                    self.ready_bitmap &= !(1 << priority);
+                }
+                return result;
+            }
+        }
+        self.waiting.remove_by(|item| item == id)
+    }
+}
+
+impl<'a> PriorityScheduler<'a> {
+    pub fn new() -> PriorityScheduler<'a> {
+        PriorityScheduler {
+            ready: [(); PRIORITY_LEVELS].map(|_| ProcessList::new()),
+            ready_bitmap: 0,
+            waiting: ProcessList::new(),
+        }
+    }
+
+    fn highest_priority(&self) -> Option<usize> {
+        if self.ready_bitmap == 0 {
+            None
+        } else {
+            Some(self.ready_bitmap.trailing_zeros() as usize)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process_list::{ProcessEntry, DEFAULT_PRIORITY};
+    use util::linked_list::ListItem;
+
+    fn item<'a>(id: u32, priority: usize) -> ProcessListItem<'a> {
+        ListItem::create(ProcessEntry::with_priority(ProcessId(id), priority).unwrap())
+    }
+
+    fn current(scheduler: &mut PriorityScheduler) -> Option<u32> {
+        scheduler.get_current_proc().map(|id| id.0)
+    }
+
+    #[test]
+    fn test_priority_order() {
+        let mut scheduler = PriorityScheduler::new();
+        let mut item0 = item(0, 5);
+        let mut item1 = item(1, 1);
+        let mut item2 = item(2, 3);
+        scheduler.push(&mut item0);
+        scheduler.push(&mut item1);
+        scheduler.push(&mut item2);
+
+        let expected = [1, 2, 0];
+        for id in expected.iter() {
+            assert_eq!(Some(*id), current(&mut scheduler));
+            let item = scheduler.pop_current_proc().unwrap();
+            assert_eq!(*id, item.0);
+        }
+        assert!(current(&mut scheduler).is_none());
+        assert!(scheduler.pop_current_proc().is_none());
+    }
+
+    #[test]
+    fn test_round_robin_same_priority() {
+        let mut scheduler = PriorityScheduler::new();
+        let mut item0 = item(0, 2);
+        let mut item1 = item(1, 2);
+        let mut item2 = item(2, 4);
+        scheduler.push(&mut item0);
+        scheduler.push(&mut item1);
+        scheduler.push(&mut item2);
+
+        assert_eq!(Some(0), current(&mut scheduler));
+        scheduler.schedule_next();
+        assert_eq!(Some(1), current(&mut scheduler));
+        scheduler.schedule_next();
+        assert_eq!(Some(0), current(&mut scheduler));
+    }
+
+    #[test]
+    fn test_preempt_on_resume_list() {
+        let mut scheduler = PriorityScheduler::new();
+        let mut item0 = item(0, 10);
+        let mut item1 = item(1, 0);
+        scheduler.push(&mut item0);
+        scheduler.push(&mut item1);
+        assert_eq!(Some(1), current(&mut scheduler));
+
+        // the high priority process waits for an interrupt
+        let waiting = scheduler.pop_current_proc().unwrap();
+        assert_eq!(Some(0), current(&mut scheduler));
+
+        let mut released = ProcessList::new();
+        released.push(waiting);
+        scheduler.resume_list(&mut released);
+        assert!(released.is_empty());
+        assert_eq!(Some(1), current(&mut scheduler));
+        scheduler.pop_current_proc().unwrap();
+        assert_eq!(Some(0), current(&mut scheduler));
+    }
+
+    #[test]
+    fn test_preempt_on_resume_waiting() {
+        let mut scheduler = PriorityScheduler::new();
+        let mut item0 = item(0, 7);
+        let mut item1 = item(1, 3);
+        let mut item2 = item(2, 5);
+        scheduler.push(&mut item0);
+        scheduler.push_wait(&mut item1);
+        scheduler.push_wait(&mut item2);
+        assert_eq!(Some(0), current(&mut scheduler));
+
+        scheduler.resume_waiting();
+        assert_eq!(Some(1), current(&mut scheduler));
+        scheduler.pop_current_proc().unwrap();
+        assert_eq!(Some(2), current(&mut scheduler));
+        scheduler.pop_current_proc().unwrap();
+        assert_eq!(Some(0), current(&mut scheduler));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut scheduler = PriorityScheduler::new();
+        let mut item0 = item(0, 1);
+        let mut item1 = item(1, 2);
+        let mut item2 = ListItem::create(ProcessEntry::new(ProcessId(2)));
+        scheduler.push(&mut item0);
+        scheduler.push(&mut item1);
+        scheduler.push_wait(&mut item2);
+
+        assert_eq!(0, scheduler.remove(&ProcessId(0)).unwrap().0);
+        assert!(scheduler.remove(&ProcessId(0)).is_none());
+        assert_eq!(Some(1), current(&mut scheduler));
+        assert_eq!(2, scheduler.remove(&ProcessId(2)).unwrap().0);
+        scheduler.resume_waiting();
+        assert_eq!(Some(1), current(&mut scheduler));
+        assert_eq!(1, scheduler.remove(&ProcessId(1)).unwrap().0);
+        assert!(current(&mut scheduler).is_none());
+    }
+
+    #[test]
+    fn test_default_priority() {
+        let mut scheduler = PriorityScheduler::new();
+        let mut item0 = ListItem::create(ProcessEntry::new(ProcessId(0)));
+        let mut item1 = item(1, DEFAULT_PRIORITY - 1);
+        // the priority does not depend on the id
+        let mut item2 = ListItem::create(ProcessEntry::new(ProcessId(100)));
+        assert_eq!(DEFAULT_PRIORITY, item0.priority());
+        assert_eq!(DEFAULT_PRIORITY, item2.priority());
+        scheduler.push(&mut item0);
+        scheduler.push(&mut item2);
+        scheduler.push(&mut item1);
+        assert_eq!(Some(1), current(&mut scheduler));
+        assert_eq!(100, scheduler.remove(&ProcessId(100)).unwrap().0);
+    }
+
+    #[test]
+    fn test_invalid_priority() {
+        assert!(ProcessEntry::with_priority(ProcessId(0), PRIORITY_LEVELS).is_err());
+        assert!(ProcessEntry::with_priority(ProcessId(0), PRIORITY_LEVELS - 1).is_ok());
+    }
+}
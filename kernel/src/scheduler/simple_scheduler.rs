@@ -1,19 +1,20 @@
 use core::ops::DerefMut;
 use core::option::Option;
 
-use super::{ExecResult, Scheduler};
-use crate::process_list::ProcessListItem;
+use super::Scheduler;
+use crate::process_list::{ProcessList, ProcessListItem};
 use crate::process_manager::ProcessId;
-use util::linked_list::{LinkedList, ListItem};
 
 pub struct SimpleScheduler<'a> {
-    active: LinkedList<'a, ProcessId>,
-    waiting: LinkedList<'a, ProcessId>,
+    active: ProcessList<'a>,
+    waiting: ProcessList<'a>,
 }
 
 impl<'a> Scheduler<'a> for SimpleScheduler<'a> {
     fn get_current_proc(&mut self) -> Option<&mut ProcessId> {
-        self.active.head_mut().map(|item| (*item).deref_mut())
+        self.active
+            .head_mut()
+            .map(|item| (*item).deref_mut().deref_mut())
     }
 
     fn pop_current_proc(&mut self) -> Option<&'a mut ProcessListItem<'a>> {
@@ -27,28 +28,38 @@ impl<'a> Scheduler<'a> for SimpleScheduler<'a> {
         }
     }
 
-    fn resume_list(&mut self, process_list: &mut LinkedList<'a, ProcessId>) {
+    fn resume_list(&mut self, process_list: &mut ProcessList<'a>) {
         self.active.join(process_list);
     }
 
-    fn push(&mut self, proc: &'a mut ListItem<'a, ProcessId>) {
+    fn push(&mut self, proc: &'a mut ProcessListItem<'a>) {
         self.active.push(proc);
     }
 
-    fn push_wait(&mut self, proc: &'a mut ListItem<'a, ProcessId>) {
+    fn push_wait(&mut self, proc: &'a mut ProcessListItem<'a>) {
         self.waiting.push(proc);
     }
 
     fn resume_waiting(&mut self) {
         self.active.join(&mut self.waiting);
     }
+
+    fn has_waiting(&self) -> bool {
+        !self.waiting.is_empty()
+    }
+
+    fn remove(&mut self, id: &ProcessId) -> Option<&'a mut ProcessListItem<'a>> {
+        self.active
+            .remove_by(|item| item == id)
+            .or_else(|| self.waiting.remove_by(|item| item == id))
+    }
 }
 
 impl<'a> SimpleScheduler<'a> {
     pub fn new() -> SimpleScheduler<'a> {
         SimpleScheduler {
-            active: LinkedList::new(),
-            waiting: LinkedList::new(),
+            active: ProcessList::new(),
+            waiting: ProcessList::new(),
         }
     }
 }
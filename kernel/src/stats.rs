@@ -0,0 +1,53 @@
+use arch::dwt::Dwt;
+
+// kinds of the STATS syscall
+pub const PROCESS_STATS: u32 = 0;
+pub const SYSCALL_STATS: u32 = 1;
+pub const IRQ_STATS: u32 = 2;
+
+// syscall ids are below this
+pub const SYSCALL_COUNT: usize = 45;
+
+// how often something ran and the cycles of the DWT cycle counter it took
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stats {
+    pub count: u32,
+    pub cycles: u64,
+}
+
+impl Stats {
+    pub const fn new() -> Stats {
+        Stats {
+            count: 0,
+            cycles: 0,
+        }
+    }
+
+    pub fn add(&mut self, cycles: u32) {
+        self.count = self.count.wrapping_add(1);
+        self.cycles += cycles as u64;
+    }
+}
+
+pub fn cycle_count() -> u32 {
+    Dwt::new().cycle_count()
+}
+
+// correct as long as less than one period of the counter passed
+pub fn cycles_since(start: u32) -> u32 {
+    cycle_count().wrapping_sub(start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add() {
+        let mut stats = Stats::new();
+        stats.add(100);
+        stats.add(u32::MAX);
+        assert_eq!(2, stats.count);
+        assert_eq!(100 + u32::MAX as u64, stats.cycles);
+    }
+}
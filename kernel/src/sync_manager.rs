@@ -0,0 +1,230 @@
+use crate::process_list::{ProcessList, ProcessListItem};
+use crate::process_manager::ProcessId;
+
+const MAX_SYNC_OBJECTS: usize = 16;
+
+enum SyncKind {
+    Mutex { owner: Option<ProcessId> },
+    Semaphore { count: u32 },
+}
+
+struct SyncObject<'a> {
+    kind: SyncKind,
+    waiting: ProcessList<'a>,
+}
+
+pub struct SyncManager<'a> {
+    objects: [Option<SyncObject<'a>>; MAX_SYNC_OBJECTS],
+    object_count: usize,
+}
+
+impl<'a> SyncManager<'a> {
+    pub fn new() -> SyncManager<'a> {
+        SyncManager {
+            objects: [(); MAX_SYNC_OBJECTS].map(|_| None),
+            object_count: 0,
+        }
+    }
+
+    pub fn create_mutex(&mut self) -> Option<u32> {
+        self.create(SyncKind::Mutex { owner: None })
+    }
+
+    pub fn create_semaphore(&mut self, count: u32) -> Option<u32> {
+        self.create(SyncKind::Semaphore { count })
+    }
+
+    // returns false if the caller has to wait with push_wait
+    pub fn lock(&mut self, handle: u32, id: &ProcessId) -> Result<bool, ()> {
+        match self.get_mut(handle)?.kind {
+            SyncKind::Mutex { ref mut owner } => match owner {
+                None => {
+                    owner.replace(id.clone());
+                    Ok(true)
+                }
+                Some(owner) if owner == id => Err(()),
+                Some(_) => Ok(false),
+            },
+            _ => Err(()),
+        }
+    }
+
+    // returns the waiting process which takes over the mutex
+    pub fn unlock(
+        &mut self,
+        handle: u32,
+        id: &ProcessId,
+    ) -> Result<Option<&'a mut ProcessListItem<'a>>, ()> {
+        let object = self.get_mut(handle)?;
+        match object.kind {
+            SyncKind::Mutex { ref mut owner } if owner.as_ref() == Some(id) => {
+                let next = object.waiting.pop();
+                *owner = next.as_ref().map(|item| item.item.clone());
+                Ok(next)
+            }
+            _ => Err(()),
+        }
+    }
+
+    // returns false if the caller has to wait with push_wait
+    pub fn wait(&mut self, handle: u32) -> Result<bool, ()> {
+        match self.get_mut(handle)?.kind {
+            SyncKind::Semaphore { ref mut count } => {
+                if *count > 0 {
+                    *count -= 1;
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
+            _ => Err(()),
+        }
+    }
+
+    // returns the waiting process which gets the count
+    pub fn signal(&mut self, handle: u32) -> Result<Option<&'a mut ProcessListItem<'a>>, ()> {
+        let object = self.get_mut(handle)?;
+        match object.kind {
+            SyncKind::Semaphore { ref mut count } => {
+                let next = object.waiting.pop();
+                if next.is_none() {
+                    *count = count.checked_add(1).ok_or(())?;
+                }
+                Ok(next)
+            }
+            _ => Err(()),
+        }
+    }
+
+    pub fn push_wait(&mut self, handle: u32, item: &'a mut ProcessListItem<'a>) {
+        self.get_mut(handle).unwrap().waiting.push(item);
+    }
+
+    pub fn remove_wait(&mut self, id: &ProcessId) -> Option<&'a mut ProcessListItem<'a>> {
+        for object in self.objects.iter_mut().flatten() {
+            let result = object.waiting.remove_by(|item| item == id);
+            if result.is_some() {
+                return result;
+            }
+        }
+        None
+    }
+
+    // unlock the mutexes owned by the process and return the new owners
+    pub fn release(&mut self, id: &ProcessId) -> ProcessList<'a> {
+        let mut process_list = ProcessList::new();
+        for object in self.objects.iter_mut().flatten() {
+            match object.kind {
+                SyncKind::Mutex { ref mut owner } if owner.as_ref() == Some(id) => {
+                    let next = object.waiting.pop();
+                    *owner = next.as_ref().map(|item| item.item.clone());
+                    if let Some(item) = next {
+                        process_list.push(item);
+                    }
+                }
+                _ => {}
+            }
+        }
+        process_list
+    }
+
+    fn create(&mut self, kind: SyncKind) -> Option<u32> {
+        if self.object_count >= MAX_SYNC_OBJECTS {
+            return None;
+        }
+        self.objects[self.object_count] = Some(SyncObject {
+            kind,
+            waiting: ProcessList::new(),
+        });
+        self.object_count += 1;
+        Some(self.object_count as u32 - 1)
+    }
+
+    fn get_mut(&mut self, handle: u32) -> Result<&mut SyncObject<'a>, ()> {
+        self.objects
+            .get_mut(handle as usize)
+            .and_then(|object| object.as_mut())
+            .ok_or(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process_list::ProcessEntry;
+    use util::linked_list::ListItem;
+
+    #[test]
+    fn test_mutex() {
+        let mut sync_manager = SyncManager::new();
+        let mutex = sync_manager.create_mutex().unwrap();
+        let mut item1 = ListItem::create(ProcessEntry::new(ProcessId(1)));
+        let mut item2 = ListItem::create(ProcessEntry::new(ProcessId(2)));
+
+        assert_eq!(Ok(true), sync_manager.lock(mutex, &ProcessId(0)));
+        assert_eq!(Err(()), sync_manager.lock(mutex, &ProcessId(0)));
+        assert_eq!(Ok(false), sync_manager.lock(mutex, &ProcessId(1)));
+        sync_manager.push_wait(mutex, &mut item1);
+        assert_eq!(Ok(false), sync_manager.lock(mutex, &ProcessId(2)));
+        sync_manager.push_wait(mutex, &mut item2);
+
+        assert!(sync_manager.unlock(mutex, &ProcessId(1)).is_err());
+        let next = sync_manager.unlock(mutex, &ProcessId(0)).unwrap();
+        assert_eq!(1, next.unwrap().0);
+        assert!(sync_manager.unlock(mutex, &ProcessId(0)).is_err());
+        let next = sync_manager.unlock(mutex, &ProcessId(1)).unwrap();
+        assert_eq!(2, next.unwrap().0);
+        assert!(sync_manager.unlock(mutex, &ProcessId(2)).unwrap().is_none());
+        assert_eq!(Ok(true), sync_manager.lock(mutex, &ProcessId(1)));
+    }
+
+    #[test]
+    fn test_semaphore() {
+        let mut sync_manager = SyncManager::new();
+        let semaphore = sync_manager.create_semaphore(1).unwrap();
+        let mut item1 = ListItem::create(ProcessEntry::new(ProcessId(1)));
+
+        assert_eq!(Ok(true), sync_manager.wait(semaphore));
+        assert_eq!(Ok(false), sync_manager.wait(semaphore));
+        sync_manager.push_wait(semaphore, &mut item1);
+        assert_eq!(1, sync_manager.signal(semaphore).unwrap().unwrap().0);
+        assert!(sync_manager.signal(semaphore).unwrap().is_none());
+        assert!(sync_manager.signal(semaphore).unwrap().is_none());
+        assert_eq!(Ok(true), sync_manager.wait(semaphore));
+        assert_eq!(Ok(true), sync_manager.wait(semaphore));
+        assert_eq!(Ok(false), sync_manager.wait(semaphore));
+    }
+
+    #[test]
+    fn test_invalid_handle() {
+        let mut sync_manager = SyncManager::new();
+        let mutex = sync_manager.create_mutex().unwrap();
+        let semaphore = sync_manager.create_semaphore(0).unwrap();
+        assert!(sync_manager.lock(semaphore, &ProcessId(0)).is_err());
+        assert!(sync_manager.wait(mutex).is_err());
+        assert!(sync_manager.signal(mutex).is_err());
+        assert!(sync_manager
+            .lock(MAX_SYNC_OBJECTS as u32, &ProcessId(0))
+            .is_err());
+        assert!(sync_manager.wait(semaphore + 1).is_err());
+    }
+
+    #[test]
+    fn test_release() {
+        let mut sync_manager = SyncManager::new();
+        let mutex1 = sync_manager.create_mutex().unwrap();
+        let mutex2 = sync_manager.create_mutex().unwrap();
+        let mut item1 = ListItem::create(ProcessEntry::new(ProcessId(1)));
+
+        assert_eq!(Ok(true), sync_manager.lock(mutex1, &ProcessId(0)));
+        assert_eq!(Ok(true), sync_manager.lock(mutex2, &ProcessId(0)));
+        assert_eq!(Ok(false), sync_manager.lock(mutex1, &ProcessId(1)));
+        sync_manager.push_wait(mutex1, &mut item1);
+
+        let mut owners = sync_manager.release(&ProcessId(0));
+        assert_eq!(1, owners.pop().unwrap().0);
+        assert!(owners.is_empty());
+        assert!(sync_manager.unlock(mutex1, &ProcessId(1)).is_ok());
+        assert_eq!(Ok(true), sync_manager.lock(mutex2, &ProcessId(2)));
+    }
+}
@@ -0,0 +1,73 @@
+use crate::process_allocator::SpawnError;
+
+// r0 of a finished syscall, any other value is a SysError
+pub const SUCCESS: u32 = 0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SysError {
+    InvalidSyscall = 1,
+    InvalidArgument = 2,
+    NoSuchProcess = 3,
+    ResourceExhausted = 4,
+    NoMemory = 5,
+    InvalidAddress = 6,
+    AlreadyExists = 7,
+    WouldBlock = 8,
+    // the process being waited for exited
+    Aborted = 9,
+    // the timeout of a blocking syscall passed
+    TimedOut = 10,
+}
+
+impl SysError {
+    pub fn from_u32(code: u32) -> Option<SysError> {
+        match code {
+            1 => Some(SysError::InvalidSyscall),
+            2 => Some(SysError::InvalidArgument),
+            3 => Some(SysError::NoSuchProcess),
+            4 => Some(SysError::ResourceExhausted),
+            5 => Some(SysError::NoMemory),
+            6 => Some(SysError::InvalidAddress),
+            7 => Some(SysError::AlreadyExists),
+            8 => Some(SysError::WouldBlock),
+            9 => Some(SysError::Aborted),
+            10 => Some(SysError::TimedOut),
+            _ => None,
+        }
+    }
+}
+
+impl From<SpawnError> for SysError {
+    fn from(error: SpawnError) -> SysError {
+        match error {
+            SpawnError::NoMemory => SysError::NoMemory,
+            SpawnError::NoProcessSlot => SysError::ResourceExhausted,
+            SpawnError::InvalidPriority => SysError::InvalidArgument,
+        }
+    }
+}
+
+// unknown codes are reported as InvalidSyscall
+pub fn into_result(code: u32) -> Result<(), SysError> {
+    if code == SUCCESS {
+        Ok(())
+    } else {
+        Err(SysError::from_u32(code).unwrap_or(SysError::InvalidSyscall))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_result() {
+        assert_eq!(Ok(()), into_result(SUCCESS));
+        for code in 1..=10 {
+            let error = SysError::from_u32(code).unwrap();
+            assert_eq!(code, error as u32);
+            assert_eq!(Err(error), into_result(code));
+        }
+        assert_eq!(Err(SysError::InvalidSyscall), into_result(0xff));
+    }
+}
@@ -5,3 +5,40 @@ pub const WAIT_SYSTICK: u32 = 4;
 pub const DORMANT: u32 = 5;
 pub const SEND_MESSAGE: u32 = 6;
 pub const RECEIVE_MESSAGE: u32 = 7;
+pub const SLEEP: u32 = 8;
+pub const SLEEP_UNTIL: u32 = 9;
+pub const EXIT: u32 = 10;
+pub const KILL: u32 = 11;
+pub const WAIT: u32 = 12;
+pub const SPAWN: u32 = 13;
+pub const WAIT_MESSAGE: u32 = 14;
+pub const SEND_BUF: u32 = 15;
+pub const RECV_BUF: u32 = 16;
+pub const CALL: u32 = 17;
+pub const RECEIVE_CALL: u32 = 18;
+pub const REPLY: u32 = 19;
+pub const REGISTER_NAME: u32 = 20;
+pub const LOOKUP: u32 = 21;
+pub const MUTEX_CREATE: u32 = 22;
+pub const MUTEX_LOCK: u32 = 23;
+pub const MUTEX_UNLOCK: u32 = 24;
+pub const SEMAPHORE_CREATE: u32 = 25;
+pub const SEMAPHORE_WAIT: u32 = 26;
+pub const SEMAPHORE_SIGNAL: u32 = 27;
+pub const NOTIFY: u32 = 28;
+pub const WAIT_FLAGS: u32 = 29;
+pub const STATS: u32 = 30;
+pub const NOW: u32 = 31;
+pub const TIMER_CREATE: u32 = 32;
+pub const TIMER_CANCEL: u32 = 33;
+pub const WAIT_IRQ_TIMEOUT: u32 = 34;
+pub const WAIT_MESSAGE_TIMEOUT: u32 = 35;
+pub const LOOKUP_TIMEOUT: u32 = 36;
+pub const MUTEX_LOCK_TIMEOUT: u32 = 37;
+pub const SEMAPHORE_WAIT_TIMEOUT: u32 = 38;
+pub const WAIT_FLAGS_TIMEOUT: u32 = 39;
+pub const CALL_TIMEOUT: u32 = 40;
+pub const RECEIVE_CALL_TIMEOUT: u32 = 41;
+pub const SEND_BUF_TIMEOUT: u32 = 42;
+pub const RECV_BUF_TIMEOUT: u32 = 43;
+pub const WAIT_TIMEOUT: u32 = 44;
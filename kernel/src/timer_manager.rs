@@ -0,0 +1,339 @@
+use crate::process_list::{ProcessList, ProcessListItem};
+use crate::process_manager::ProcessId;
+use util::linked_list::{LinkedList, ListItem};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimerMode {
+    OneShot = 0,
+    Periodic = 1,
+}
+
+impl TimerMode {
+    pub fn from_u32(mode: u32) -> TimerMode {
+        if mode == TimerMode::Periodic as u32 {
+            TimerMode::Periodic
+        } else {
+            TimerMode::OneShot
+        }
+    }
+}
+
+// a timer created with timer_create, it posts the message to the target
+pub struct MessageTimer {
+    id: u32,
+    owner: ProcessId,
+    target: ProcessId,
+    message: u32,
+    // None for a one-shot timer
+    period: Option<u64>,
+}
+
+pub enum TimerAction<'a> {
+    Wakeup(&'a mut ProcessListItem<'a>),
+    Message(MessageTimer),
+    // the process waits in a blocking syscall, which the kernel fails
+    Timeout(ProcessId),
+}
+
+// what the kernel has to do for an expired timer
+pub enum Expired {
+    Message(ProcessId, u32),
+    Timeout(ProcessId),
+}
+
+pub struct Timer<'a> {
+    deadline: u64,
+    action: Option<TimerAction<'a>>,
+}
+
+pub struct TimerManager<'a> {
+    buff: LinkedList<'a, Timer<'a>>,
+    // sorted by deadline
+    active: LinkedList<'a, Timer<'a>>,
+    ticks: u64,
+    next_id: u32,
+}
+
+impl<'a> TimerManager<'a> {
+    pub fn new(buff: &'a mut [ListItem<'a, Timer<'a>>]) -> TimerManager<'a> {
+        let mut list = LinkedList::new();
+        for item in buff.iter_mut() {
+            list.push(item);
+        }
+
+        TimerManager {
+            buff: list,
+            active: LinkedList::new(),
+            ticks: 0,
+            next_id: 0,
+        }
+    }
+
+    pub fn get_ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.buff.is_empty()
+    }
+
+    // the caller must check `is_full` beforehand
+    pub fn sleep_until(&mut self, deadline: u64, process: &'a mut ProcessListItem<'a>) {
+        self.add(deadline, TimerAction::Wakeup(process));
+    }
+
+    pub fn cancel_wakeup(&mut self, id: &ProcessId) -> Option<&'a mut ProcessListItem<'a>> {
+        let item = self.active.remove_by(|timer| match &timer.action {
+            Some(TimerAction::Wakeup(process)) => process.item == *id,
+            _ => false,
+        })?;
+        let result = match item.action.take() {
+            Some(TimerAction::Wakeup(process)) => Some(process),
+            _ => None,
+        };
+        self.buff.push(item);
+        result
+    }
+
+    // the caller must check `is_full` beforehand, returns the id of the timer
+    pub fn create_timer(
+        &mut self,
+        owner: &ProcessId,
+        target: &ProcessId,
+        message: u32,
+        deadline: u64,
+        period: Option<u64>,
+    ) -> u32 {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        let timer = MessageTimer {
+            id,
+            owner: owner.clone(),
+            target: target.clone(),
+            message,
+            period,
+        };
+        self.add(deadline, TimerAction::Message(timer));
+        id
+    }
+
+    // the caller must check `is_full` beforehand
+    pub fn set_timeout(&mut self, deadline: u64, id: &ProcessId) {
+        self.add(deadline, TimerAction::Timeout(id.clone()));
+    }
+
+    pub fn cancel_timeout(&mut self, id: &ProcessId) {
+        if let Some(item) = self.active.remove_by(|timer| match &timer.action {
+            Some(TimerAction::Timeout(process)) => process == id,
+            _ => false,
+        }) {
+            item.action = None;
+            self.buff.push(item);
+        }
+    }
+
+    // only the owner can cancel a timer
+    pub fn cancel_timer(&mut self, id: u32, owner: &ProcessId) -> Result<(), ()> {
+        let item = self
+            .active
+            .remove_by(|timer| match &timer.action {
+                Some(TimerAction::Message(timer)) => timer.id == id && timer.owner == *owner,
+                _ => false,
+            })
+            .ok_or(())?;
+        item.action = None;
+        self.buff.push(item);
+        Ok(())
+    }
+
+    // cancel the timers created by, posting to or timing out a terminating process
+    pub fn release(&mut self, id: &ProcessId) {
+        while let Some(item) = self.active.remove_by(|timer| match &timer.action {
+            Some(TimerAction::Message(timer)) => timer.owner == *id || timer.target == *id,
+            Some(TimerAction::Timeout(process)) => process == id,
+            _ => false,
+        }) {
+            item.action = None;
+            self.buff.push(item);
+        }
+    }
+
+    // advance the tick count and return the processes whose deadline has passed,
+    // `on_expired` is called for the other expired timers
+    pub fn tick<F>(&mut self, elapsed: u64, mut on_expired: F) -> ProcessList<'a>
+    where
+        F: FnMut(Expired),
+    {
+        self.ticks += elapsed;
+        let mut process_list = ProcessList::new();
+        while self
+            .next_deadline()
+            .map_or(false, |deadline| deadline <= self.ticks)
+        {
+            let item = self.active.pop().unwrap();
+            match item.action.take() {
+                Some(TimerAction::Wakeup(process)) => process_list.push(process),
+                Some(TimerAction::Message(timer)) => {
+                    on_expired(Expired::Message(timer.target.clone(), timer.message));
+                    if let Some(period) = timer.period {
+                        // the periods missed while the kernel was busy are skipped
+                        let missed = (self.ticks - item.deadline) / period;
+                        item.deadline += (missed + 1) * period;
+                        item.action = Some(TimerAction::Message(timer));
+                        self.active
+                            .insert_by(item, |timer, element| timer.deadline < element.deadline);
+                        continue;
+                    }
+                }
+                Some(TimerAction::Timeout(id)) => on_expired(Expired::Timeout(id)),
+                None => {}
+            }
+            self.buff.push(item);
+        }
+        process_list
+    }
+
+    pub fn next_deadline(&mut self) -> Option<u64> {
+        self.active.head_mut().map(|item| item.deadline)
+    }
+
+    fn add(&mut self, deadline: u64, action: TimerAction<'a>) {
+        let item = self.buff.pop().unwrap();
+        item.item = Timer {
+            deadline,
+            action: Some(action),
+        };
+        self.active
+            .insert_by(item, |timer, element| timer.deadline < element.deadline);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process_list::ProcessEntry;
+    use core::mem::MaybeUninit;
+
+    #[test]
+    fn test_wakeup_order() {
+        let mut buff: [ListItem<Timer>; 4] = unsafe { MaybeUninit::uninit().assume_init() };
+        let mut timer_manager = TimerManager::new(&mut buff);
+        let mut item0 = ListItem::create(ProcessEntry::new(ProcessId(0)));
+        let mut item1 = ListItem::create(ProcessEntry::new(ProcessId(1)));
+        let mut item2 = ListItem::create(ProcessEntry::new(ProcessId(2)));
+        timer_manager.sleep_until(5, &mut item0);
+        timer_manager.sleep_until(2, &mut item1);
+        timer_manager.sleep_until(5, &mut item2);
+        assert_eq!(Some(2), timer_manager.next_deadline());
+
+        assert!(timer_manager.tick(1, |_| {}).is_empty());
+        let mut expired = timer_manager.tick(1, |_| {});
+        assert_eq!(1, expired.pop().unwrap().0);
+        assert!(expired.is_empty());
+
+        assert!(timer_manager.tick(2, |_| {}).is_empty());
+        let mut expired = timer_manager.tick(1, |_| {});
+        assert_eq!(5, timer_manager.get_ticks());
+        assert_eq!(0, expired.pop().unwrap().0);
+        assert_eq!(2, expired.pop().unwrap().0);
+        assert!(expired.is_empty());
+        assert!(timer_manager.next_deadline().is_none());
+    }
+
+    #[test]
+    fn test_cancel_wakeup() {
+        let mut buff: [ListItem<Timer>; 2] = unsafe { MaybeUninit::uninit().assume_init() };
+        let mut timer_manager = TimerManager::new(&mut buff);
+        let mut item0 = ListItem::create(ProcessEntry::new(ProcessId(0)));
+        let mut item1 = ListItem::create(ProcessEntry::new(ProcessId(1)));
+        timer_manager.sleep_until(3, &mut item0);
+        timer_manager.sleep_until(1, &mut item1);
+        assert!(timer_manager.is_full());
+
+        assert_eq!(1, timer_manager.cancel_wakeup(&ProcessId(1)).unwrap().0);
+        assert!(timer_manager.cancel_wakeup(&ProcessId(1)).is_none());
+        assert!(!timer_manager.is_full());
+        assert_eq!(Some(3), timer_manager.next_deadline());
+        assert!(timer_manager.tick(2, |_| {}).is_empty());
+        assert_eq!(0, timer_manager.tick(1, |_| {}).pop().unwrap().0);
+    }
+
+    #[test]
+    fn test_full() {
+        let mut buff: [ListItem<Timer>; 1] = unsafe { MaybeUninit::uninit().assume_init() };
+        let mut timer_manager = TimerManager::new(&mut buff);
+        let mut item0 = ListItem::create(ProcessEntry::new(ProcessId(0)));
+        assert!(!timer_manager.is_full());
+        timer_manager.sleep_until(1, &mut item0);
+        assert!(timer_manager.is_full());
+        timer_manager.tick(1, |_| {});
+        assert!(!timer_manager.is_full());
+    }
+
+    #[test]
+    fn test_message_timer() {
+        let mut buff: [ListItem<Timer>; 3] = unsafe { MaybeUninit::uninit().assume_init() };
+        let mut timer_manager = TimerManager::new(&mut buff);
+        let owner = ProcessId(0);
+        let target = ProcessId(1);
+        let periodic = timer_manager.create_timer(&owner, &target, 10, 2, Some(2));
+        let one_shot = timer_manager.create_timer(&owner, &target, 20, 3, None);
+        assert_ne!(periodic, one_shot);
+        assert_eq!(Err(()), timer_manager.cancel_timer(one_shot, &target));
+
+        let mut posted = [0; 4];
+        let mut count = 0;
+        let mut on_expired = |expired| match expired {
+            Expired::Message(id, message) => {
+                assert!(target == id);
+                posted[count] = message;
+                count += 1;
+            }
+            Expired::Timeout(_) => panic!(),
+        };
+        assert!(timer_manager.tick(2, &mut on_expired).is_empty());
+        timer_manager.tick(1, &mut on_expired);
+        // posted once for 4, the period at 6 is skipped
+        timer_manager.tick(4, &mut on_expired);
+        assert_eq!(Some(8), timer_manager.next_deadline());
+        assert_eq!([10, 20, 10, 0], posted);
+
+        assert_eq!(Err(()), timer_manager.cancel_timer(one_shot, &owner));
+        assert_eq!(Ok(()), timer_manager.cancel_timer(periodic, &owner));
+        assert!(timer_manager.next_deadline().is_none());
+    }
+
+    #[test]
+    fn test_release() {
+        let mut buff: [ListItem<Timer>; 3] = unsafe { MaybeUninit::uninit().assume_init() };
+        let mut timer_manager = TimerManager::new(&mut buff);
+        let mut item0 = ListItem::create(ProcessEntry::new(ProcessId(0)));
+        timer_manager.create_timer(&ProcessId(1), &ProcessId(2), 0, 1, None);
+        timer_manager.create_timer(&ProcessId(2), &ProcessId(1), 0, 2, Some(1));
+        timer_manager.sleep_until(3, &mut item0);
+        assert!(timer_manager.is_full());
+
+        timer_manager.release(&ProcessId(1));
+        assert!(!timer_manager.is_full());
+        assert_eq!(Some(3), timer_manager.next_deadline());
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut buff: [ListItem<Timer>; 2] = unsafe { MaybeUninit::uninit().assume_init() };
+        let mut timer_manager = TimerManager::new(&mut buff);
+        timer_manager.set_timeout(1, &ProcessId(0));
+        timer_manager.set_timeout(2, &ProcessId(1));
+        timer_manager.cancel_timeout(&ProcessId(0));
+        assert!(!timer_manager.is_full());
+
+        let mut timed_out = None;
+        timer_manager.tick(2, |expired| {
+            if let Expired::Timeout(id) = expired {
+                timed_out.replace(id.0);
+            }
+        });
+        assert_eq!(Some(1), timed_out);
+        assert!(timer_manager.next_deadline().is_none());
+    }
+}
@@ -0,0 +1,140 @@
+#![no_std]
+#![no_main]
+
+use arch::mpu::{AccessPermission, MemoryType, Region};
+use arch::nvic::Nvic;
+use core::marker::PhantomData;
+use cortex_m_semihosting::hio::HStdout;
+use cortex_m_semihosting::{debug, hio};
+use embedded_hal::serial::Write;
+use kernel::clock::Clock;
+use kernel::interrupt_manager::InterruptManager;
+use kernel::kernel::Kernel;
+use kernel::message_manager::MessageManager;
+use kernel::name_registry::{Name, NameRegistry};
+use kernel::process::Process;
+use kernel::process_allocator::ProcessAllocator;
+use kernel::process_manager::{ProcessId, ProcessManager};
+use kernel::scheduler::priority_scheduler::PriorityScheduler;
+use kernel::scheduler::Scheduler;
+use kernel::timer_manager::{Timer, TimerManager};
+use kernel::{process_create, process_register};
+use rt::entry;
+use rt::Vector;
+use user::println;
+use user::syscall::{dormant, wait_for_interrupt};
+use util::avl_tree::Node;
+use util::linked_list::ListItem;
+
+entry!(main);
+
+// CMSDK timer 0 of the MPS2 board
+const TIMER0: u32 = 0x4000_0000;
+const TIMER0_IRQ: u32 = 8;
+const TIMER_CTRL: *mut u32 = TIMER0 as *mut u32;
+const TIMER_VALUE: *const u32 = (TIMER0 + 0x04) as *const u32;
+const TIMER_RELOAD: *mut u32 = (TIMER0 + 0x08) as *mut u32;
+const TIMER_INTCLEAR: *mut u32 = (TIMER0 + 0x0c) as *mut u32;
+// 1ms with the 25MHz peripheral clock
+const RELOAD: u32 = 25_000;
+const SAMPLES: u32 = 32;
+
+#[link_section = ".uninit"]
+static mut PROCESS_HEAP: [u8; 1024] = [0; 1024];
+#[link_section = ".irq_table"]
+#[used]
+#[no_mangle]
+pub static mut IRQS: [Vector; 9] = [Vector { reserved: 0 }; 9];
+
+struct SemihostSerial {
+    hstdout: HStdout,
+}
+
+impl Write<char> for SemihostSerial {
+    type Error = PhantomData<SemihostSerial>;
+
+    fn write(&mut self, c: char) -> nb::Result<(), Self::Error> {
+        let _ = self.hstdout.write_all(&[c as u8]);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+fn main() -> ! {
+    let serial = SemihostSerial {
+        hstdout: hio::hstdout().unwrap(),
+    };
+    let mut scheduler = PriorityScheduler::new();
+    let mut process_manager = ProcessManager::new();
+    let mut interrupt_manager = InterruptManager::<1>::create(Nvic::new());
+    let access = AccessPermission::FullAccess;
+    let mut measure = process_create!(measure_main, 1024);
+    measure.add_region(Region::new(TIMER0, 0x1000, access, MemoryType::Device, false).unwrap());
+    let busy = process_create!(busy_main, 1024);
+    process_register!(scheduler, process_manager, measure, priority = 0);
+    process_register!(scheduler, process_manager, busy);
+    interrupt_manager.register(TIMER0_IRQ, clear_timer);
+
+    let mut message_buff: [ListItem<u32>; 4] = unsafe { core::mem::uninitialized() };
+    let message_manager = MessageManager::new(&mut message_buff, 64);
+    let mut timer_buff: [ListItem<Timer>; 1] = unsafe { core::mem::uninitialized() };
+    let timer_manager = TimerManager::new(&mut timer_buff);
+    let process_allocator = ProcessAllocator::new(unsafe { &mut PROCESS_HEAP }, 1);
+    let mut name_buff: [Node<Name, ProcessId>; 1] = unsafe { core::mem::uninitialized() };
+    let name_registry = NameRegistry::new(&mut name_buff);
+    let mut kernel = Kernel::create(
+        scheduler,
+        serial,
+        interrupt_manager,
+        process_manager,
+        message_manager,
+        timer_manager,
+        process_allocator,
+        name_registry,
+        Clock::from_calibration(100),
+    );
+
+    unsafe {
+        TIMER_RELOAD.write_volatile(RELOAD);
+        // enable with the interrupt
+        TIMER_CTRL.write_volatile(0b1001);
+    }
+    kernel.run()
+}
+
+fn clear_timer() {
+    unsafe { TIMER_INTCLEAR.write_volatile(1) };
+}
+
+// the timer counts down from RELOAD after raising the interrupt,
+// so the elapsed cycles when the process wakes up is the latency
+extern "C" fn measure_main() -> ! {
+    let (mut min, mut max, mut total) = (u32::MAX, 0, 0);
+    for _ in 0..SAMPLES {
+        wait_for_interrupt(TIMER0_IRQ).unwrap();
+        let latency = RELOAD - unsafe { TIMER_VALUE.read_volatile() };
+        min = min.min(latency);
+        max = max.max(latency);
+        total += latency;
+    }
+    println!(
+        "irq latency in timer cycles: min {} max {} avg {}",
+        min,
+        max,
+        total / SAMPLES
+    );
+    debug::exit(debug::EXIT_SUCCESS);
+    loop {
+        let _ = dormant();
+    }
+}
+
+// keeps the cpu busy so that the measure process has to preempt it
+extern "C" fn busy_main() -> ! {
+    loop {
+        core::hint::spin_loop();
+    }
+}
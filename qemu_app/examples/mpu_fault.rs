@@ -0,0 +1,109 @@
+#![no_std]
+#![no_main]
+
+use arch::nvic::Nvic;
+use core::marker::PhantomData;
+use cortex_m_semihosting::hio::HStdout;
+use cortex_m_semihosting::{debug, hio};
+use embedded_hal::serial::Write;
+use kernel::clock::Clock;
+use kernel::interrupt_manager::InterruptManager;
+use kernel::kernel::Kernel;
+use kernel::message_manager::MessageManager;
+use kernel::name_registry::{Name, NameRegistry};
+use kernel::process::{Process, FAULTED_EXIT_CODE};
+use kernel::process_allocator::ProcessAllocator;
+use kernel::process_manager::{ProcessId, ProcessManager};
+use kernel::scheduler::simple_scheduler::SimpleScheduler;
+use kernel::scheduler::Scheduler;
+use kernel::timer_manager::{Timer, TimerManager};
+use kernel::{process_create, process_register};
+use rt::entry;
+use rt::Vector;
+use user::syscall::{dormant, print_str, wait};
+use util::avl_tree::Node;
+use util::linked_list::ListItem;
+
+entry!(main);
+
+#[link_section = ".uninit"]
+static mut PROCESS_HEAP: [u8; 1024] = [0; 1024];
+#[link_section = ".irq_table"]
+#[used]
+#[no_mangle]
+pub static mut IRQS: [Vector; 1] = [Vector { reserved: 0 }];
+
+struct SemihostSerial {
+    hstdout: HStdout,
+}
+
+impl Write<char> for SemihostSerial {
+    type Error = PhantomData<SemihostSerial>;
+
+    fn write(&mut self, c: char) -> nb::Result<(), Self::Error> {
+        let _ = self.hstdout.write_all(&[c as u8]);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+fn main() -> ! {
+    let serial = SemihostSerial {
+        hstdout: hio::hstdout().unwrap(),
+    };
+    let mut scheduler = SimpleScheduler::new();
+    let mut process_manager = ProcessManager::new();
+    let mut victim = process_create!(victim_main, 1024);
+    let mut attacker = process_create!(attacker_main, 1024);
+    attacker.frame_mut().r0 = victim.stack_base;
+    process_register!(scheduler, process_manager, attacker, attacker_id);
+    victim.frame_mut().r0 = attacker_id;
+    process_register!(scheduler, process_manager, victim);
+
+    let mut message_buff: [ListItem<u32>; 4] = unsafe { core::mem::uninitialized() };
+    let message_manager = MessageManager::new(&mut message_buff, 64);
+    let mut timer_buff: [ListItem<Timer>; 1] = unsafe { core::mem::uninitialized() };
+    let timer_manager = TimerManager::new(&mut timer_buff);
+    let process_allocator = ProcessAllocator::new(unsafe { &mut PROCESS_HEAP }, 1);
+    let mut name_buff: [Node<Name, ProcessId>; 1] = unsafe { core::mem::uninitialized() };
+    let name_registry = NameRegistry::new(&mut name_buff);
+    let mut kernel = Kernel::create(
+        scheduler,
+        serial,
+        InterruptManager::<1>::create(Nvic::new()),
+        process_manager,
+        message_manager,
+        timer_manager,
+        process_allocator,
+        name_registry,
+        Clock::from_calibration(100),
+    );
+
+    kernel.run()
+}
+
+// the attacker faults and is terminated while the victim keeps running
+extern "C" fn victim_main(attacker: u32) -> ! {
+    if wait(attacker) == Ok(FAULTED_EXIT_CODE) {
+        let _ = print_str("the attacker was terminated\n");
+        debug::exit(debug::EXIT_SUCCESS);
+    } else {
+        debug::exit(debug::EXIT_FAILURE);
+    }
+    loop {
+        let _ = dormant();
+    }
+}
+
+extern "C" fn attacker_main(victim_stack: u32) -> ! {
+    let _ = print_str("reading the stack of another process\n");
+    let _ = unsafe { core::ptr::read_volatile(victim_stack as *const u32) };
+    let _ = print_str("the stack is not protected\n");
+    debug::exit(debug::EXIT_FAILURE);
+    loop {
+        let _ = dormant();
+    }
+}
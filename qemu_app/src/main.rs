@@ -8,23 +8,31 @@ use core::marker::PhantomData;
 use cortex_m_semihosting::hio::HStdout;
 use cortex_m_semihosting::{debug, hio};
 use embedded_hal::serial::Write;
+use kernel::clock::Clock;
 use kernel::interrupt_manager::InterruptManager;
 use kernel::kernel::Kernel;
 use kernel::message_manager::MessageManager;
+use kernel::name_registry::{Name, NameRegistry};
 use kernel::process::Process;
-use kernel::process_list::ProcessListItem;
+use kernel::process_allocator::ProcessAllocator;
+use kernel::process_list::{ProcessListItem, DEFAULT_PRIORITY};
 use kernel::process_manager::{ProcessId, ProcessManager};
 use kernel::scheduler::simple_scheduler::SimpleScheduler;
 use kernel::scheduler::Scheduler;
+use kernel::timer_manager::{Timer, TimerManager};
 use kernel::{process_create, process_register};
-use log::dhprintln;
 use rt::entry;
 use rt::Vector;
-use user::syscall::{dormant, wait_for_event, wait_for_interrupt};
+use user::println;
+use user::stats::print_stats;
+use user::syscall::{dormant, exit, spawn, wait, wait_for_event, wait_for_interrupt};
 use util::avl_tree::Node;
 use util::linked_list::ListItem;
 
 entry!(main);
+
+#[link_section = ".uninit"]
+static mut PROCESS_HEAP: [u8; 8192] = [0; 8192];
 #[link_section = ".irq_table"]
 #[used]
 #[no_mangle]
@@ -58,7 +66,7 @@ fn main() -> ! {
 
     let mut scheduler = SimpleScheduler::new();
     let serial = SemihostSerial { hstdout };
-    let mut interrupt_manager = InterruptManager::create(nvic);
+    let mut interrupt_manager = InterruptManager::<1>::create(nvic);
     let mut process_manager = ProcessManager::new();
     let process = process_create!(app_main, 1024);
     let process2 = process_create!(app_main2, 1024);
@@ -71,13 +79,22 @@ fn main() -> ! {
     interrupt_manager.register(0, nothing);
 
     let mut message_buff: [ListItem<u32>; 32] = unsafe { core::mem::uninitialized() };
-    let message_manager = MessageManager::new(&mut message_buff);
+    let message_manager = MessageManager::new(&mut message_buff, 256);
+    let mut timer_buff: [ListItem<Timer>; 8] = unsafe { core::mem::uninitialized() };
+    let timer_manager = TimerManager::new(&mut timer_buff);
+    let process_allocator = ProcessAllocator::new(unsafe { &mut PROCESS_HEAP }, 4);
+    let mut name_buff: [Node<Name, ProcessId>; 4] = unsafe { core::mem::uninitialized() };
+    let name_registry = NameRegistry::new(&mut name_buff);
     let mut kernel = Kernel::create(
         scheduler,
         serial,
         interrupt_manager,
         process_manager,
         message_manager,
+        timer_manager,
+        process_allocator,
+        name_registry,
+        Clock::from_calibration(100),
     );
 
     kernel.run()
@@ -86,7 +103,7 @@ fn main() -> ! {
 fn nothing() {}
 
 fn fib(n: usize) -> usize {
-    dhprintln!("fib called");
+    println!("fib called");
     if n <= 1 {
         1
     } else {
@@ -98,7 +115,7 @@ extern "C" fn app_main() -> ! {
     let message: &str = "app_main\n";
     let message_ptr = message.as_ptr();
     let length = message.bytes().len();
-    dhprintln!("fib {}: {}", 5, fib(5));
+    println!("fib {}: {}", 5, fib(5));
     unsafe {
         asm!(
             "mov r0, #1",
@@ -108,35 +125,44 @@ extern "C" fn app_main() -> ! {
             in("r2") length
         );
     }
-    wait_for_event();
-    dhprintln!("fib {}: {}", 8, fib(8));
-    dormant();
-    loop {}
+    let child = spawn(child_main, 1024, 4, DEFAULT_PRIORITY).unwrap();
+    println!("child {} exited: {:?}", child, wait(child));
+    wait_for_event().unwrap();
+    println!("fib {}: {}", 8, fib(8));
+    let _ = print_stats(8, &[0]);
+    loop {
+        let _ = dormant();
+    }
 }
+extern "C" fn child_main(n: u32) -> ! {
+    println!("fib {}: {}", n, fib(n as usize));
+    exit(n)
+}
+
 extern "C" fn app_main2() -> ! {
-    dhprintln!("fib {}: {}", 6, fib(6));
-    wait_for_event();
-    dhprintln!("fib {}: {}", 9, fib(9));
-    wait_for_event();
+    println!("fib {}: {}", 6, fib(6));
+    wait_for_event().unwrap();
+    println!("fib {}: {}", 9, fib(9));
+    wait_for_event().unwrap();
     debug::exit(debug::EXIT_SUCCESS);
 
     loop {
-        dormant();
+        let _ = dormant();
     }
 }
 
 extern "C" fn app_main3() -> ! {
-    dhprintln!("fib {}: {}", 7, fib(7));
-    wait_for_event();
-    dhprintln!("fib {}: {}", 10, fib(10));
+    println!("fib {}: {}", 7, fib(7));
+    wait_for_event().unwrap();
+    println!("fib {}: {}", 10, fib(10));
     loop {
-        dormant();
+        let _ = dormant();
     }
 }
 
 extern "C" fn app_main4() -> ! {
-    wait_for_interrupt(0);
+    wait_for_interrupt(0).unwrap();
     loop {
-        dormant();
+        let _ = dormant();
     }
 }
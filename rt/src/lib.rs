@@ -5,6 +5,7 @@
 #![feature(asm)]
 #![feature(naked_functions)]
 
+use arch::scb::Scb;
 use core::panic::PanicInfo;
 use core::ptr;
 use cortex_m_semihosting::debug;
@@ -33,8 +34,7 @@ pub unsafe extern "C" fn Reset() -> ! {
     );
     let count = &_eirq_table as *const u8 as usize - &_sirq_table as *const u8 as usize;
     ptr::write_bytes(&mut _sirq_table as *mut u8, 0, count);
-    // write to VTOR
-    ptr::write_volatile(0xE000_ED08 as *mut u32, &_svector_table as *const u8 as u32);
+    Scb::new().set_vector_table(&_svector_table as *const u8 as u32);
 
     let count = &_ebss as *const u8 as usize - &_sbss as *const u8 as usize;
     ptr::write_bytes(&mut _sbss as *mut u8, 0, count);
@@ -132,20 +132,39 @@ pub extern "C" fn DefaultExceptionHandler() {
 #[used]
 pub static mut SYSCALL_FIRED: usize = 0;
 
+// EXC_RETURN of the last exception taken from a process, it tells whether the
+// process stacked an extended frame with the FPU context
+#[no_mangle]
+#[used]
+pub static mut PROCESS_EXC_RETURN: u32 = 0;
+
 #[no_mangle]
 #[naked]
 pub unsafe extern "C" fn SVCall() {
     asm!(
-        "cmp lr, #0xfffffff9",
+        "tst lr, #4",
         "bne 1f",
         /* switch thread mode to unprivileged */
-        "movw lr, #0xfffd",
-        "movt lr, #0xffff",
+        "mrs r0, CONTROL",
+        "orr r0, r0, #1",
+        "msr CONTROL, r0",
+        /* unmask the interrupts the kernel masked with BASEPRI */
+        "mov r0, #0",
+        "msr BASEPRI, r0",
+        "isb",
+        /* asm_execute_process passes the EXC_RETURN of the process in r3 */
+        "mov lr, r3",
         "bx lr",
         "1:",
         "ldr r0, =SYSCALL_FIRED",
         "mov r1, #1",
         "str r1, [r0, #0]",
+        "ldr r0, =PROCESS_EXC_RETURN",
+        "str lr, [r0, #0]",
+        "mrs r0, CONTROL",
+        "bic r0, r0, #1",
+        "msr CONTROL, r0",
+        "isb",
 
         "movw lr, #0xfff9",
         "movt lr, #0xffff",
@@ -1,5 +1,8 @@
 #![no_std]
 #![feature(asm)]
 
+pub mod print;
+pub mod stats;
 pub mod syscall;
+pub mod time;
 pub mod util;
@@ -0,0 +1,23 @@
+use crate::syscall::print_str;
+use core::fmt::{self, Write};
+
+// writes to the serial port of the kernel with the print syscall,
+// so processes need no access to the memory of a semihosting handle
+pub struct Printer;
+
+impl Write for Printer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        print_str(s).map_err(|_| fmt::Error)
+    }
+}
+
+#[macro_export]
+macro_rules! println {
+    () => {
+        $crate::println!("")
+    };
+    ($($arg:tt)*) => {{
+        use core::fmt::Write as _;
+        let _ = writeln!($crate::print::Printer, $($arg)*);
+    }};
+}
@@ -0,0 +1,41 @@
+use crate::print::Printer;
+use crate::syscall::stats;
+use core::fmt::{self, Write};
+use kernel::stats::{IRQ_STATS, PROCESS_STATS, SYSCALL_COUNT, SYSCALL_STATS};
+
+// prints the processes below max_process, the syscalls which were called and the given IRQs
+pub fn print_stats(max_process: u32, irqs: &[u32]) -> fmt::Result {
+    let mut printer = Printer;
+    writeln!(printer, "kind    id    count      cycles")?;
+    for id in 0..max_process {
+        if let Ok(stats) = stats(PROCESS_STATS, id) {
+            writeln!(
+                printer,
+                "process {:<5} {:<10} {}",
+                id, stats.count, stats.cycles
+            )?;
+        }
+    }
+    for id in 0..SYSCALL_COUNT as u32 {
+        match stats(SYSCALL_STATS, id) {
+            Ok(stats) if stats.count > 0 => {
+                writeln!(
+                    printer,
+                    "syscall {:<5} {:<10} {}",
+                    id, stats.count, stats.cycles
+                )?;
+            }
+            _ => {}
+        }
+    }
+    for id in irqs {
+        if let Ok(stats) = stats(IRQ_STATS, *id) {
+            writeln!(
+                printer,
+                "irq     {:<5} {:<10} {}",
+                id, stats.count, stats.cycles
+            )?;
+        }
+    }
+    Ok(())
+}
@@ -1,16 +1,23 @@
+use kernel::event_manager::WaitMode;
+use kernel::stats::Stats;
+use kernel::sys_error::{into_result, SysError};
 use kernel::syscall_id::*;
+use kernel::timer_manager::TimerMode;
 
-pub fn dormant() {
+pub fn dormant() -> Result<(), SysError> {
+    let result: u32;
     unsafe {
         asm!(
             "svc 1",
+            lateout("r0") result,
             in("r0") DORMANT,
         );
     }
+    into_result(result)
 }
 
-pub fn send_message(id: u32, message: u32) -> bool {
-    let result: usize;
+pub fn send_message(id: u32, message: u32) -> Result<(), SysError> {
+    let result: u32;
     unsafe {
         asm!(
             "svc 1",
@@ -20,65 +27,638 @@ pub fn send_message(id: u32, message: u32) -> bool {
             in("r2") message,
         );
     }
-    result > 0
+    into_result(result)
 }
 
-pub fn receive_message() -> Option<u32> {
-    let result: usize;
+// fails with WouldBlock if no message is queued
+pub fn receive_message() -> Result<u32, SysError> {
+    let result: u32;
     let message: u32;
     unsafe {
         asm!(
             "svc 1",
-            lateout("r0")result,
-            out("r1") message,
+            lateout("r0") result,
+            lateout("r1") message,
             in("r0") RECEIVE_MESSAGE,
         );
     }
+    into_result(result).map(|_| message)
+}
+
+// blocks until a message arrives
+pub fn wait_message() -> Result<u32, SysError> {
+    let result: u32;
+    let message: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            lateout("r1") message,
+            in("r0") WAIT_MESSAGE,
+        );
+    }
+    into_result(result).map(|_| message)
+}
+
+// fails with TimedOut if no message arrives within the ticks
+pub fn wait_message_timeout(ticks: u32) -> Result<u32, SysError> {
+    let result: u32;
+    let message: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            lateout("r1") message,
+            in("r0") WAIT_MESSAGE_TIMEOUT,
+            in("r1") ticks,
+        );
+    }
+    into_result(result).map(|_| message)
+}
+
+pub fn send_buf(id: u32, buf: &[u8]) -> Result<(), SysError> {
+    let result: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            in("r0") SEND_BUF,
+            in("r1") id,
+            in("r2") buf.as_ptr(),
+            in("r3") buf.len(),
+        );
+    }
+    into_result(result)
+}
+
+// fails with TimedOut if the target does not receive the buffer within the ticks
+pub fn send_buf_timeout(id: u32, buf: &[u8], ticks: u32) -> Result<(), SysError> {
+    let result: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            in("r0") SEND_BUF_TIMEOUT,
+            in("r1") id,
+            in("r2") buf.as_ptr(),
+            in("r3") buf.len(),
+            in("r12") ticks,
+        );
+    }
+    into_result(result)
+}
+
+pub fn recv_buf(buf: &mut [u8]) -> Result<(u32, usize), SysError> {
+    let result: u32;
+    let sender: u32;
+    let len: usize;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            lateout("r1") sender,
+            lateout("r2") len,
+            in("r0") RECV_BUF,
+            in("r1") buf.as_mut_ptr(),
+            in("r2") buf.len(),
+        );
+    }
+    into_result(result).map(|_| (sender, len))
+}
+
+// fails with TimedOut if no buffer arrives within the ticks
+pub fn recv_buf_timeout(buf: &mut [u8], ticks: u32) -> Result<(u32, usize), SysError> {
+    let result: u32;
+    let sender: u32;
+    let len: usize;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            lateout("r1") sender,
+            lateout("r2") len,
+            in("r0") RECV_BUF_TIMEOUT,
+            in("r1") buf.as_mut_ptr(),
+            in("r2") buf.len(),
+            in("r3") ticks,
+        );
+    }
+    into_result(result).map(|_| (sender, len))
+}
+
+pub fn call(id: u32, request: u32) -> Result<u32, SysError> {
+    let result: u32;
+    let reply: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            lateout("r1") reply,
+            in("r0") CALL,
+            in("r1") id,
+            in("r2") request,
+        );
+    }
+    into_result(result).map(|_| reply)
+}
+
+// fails with TimedOut if there is no reply within the ticks
+pub fn call_timeout(id: u32, request: u32, ticks: u32) -> Result<u32, SysError> {
+    let result: u32;
+    let reply: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            lateout("r1") reply,
+            in("r0") CALL_TIMEOUT,
+            in("r1") id,
+            in("r2") request,
+            in("r3") ticks,
+        );
+    }
+    into_result(result).map(|_| reply)
+}
+
+// returns the client id and the request
+pub fn receive() -> Result<(u32, u32), SysError> {
+    let result: u32;
+    let client: u32;
+    let request: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            lateout("r1") client,
+            lateout("r2") request,
+            in("r0") RECEIVE_CALL,
+        );
+    }
+    into_result(result).map(|_| (client, request))
+}
+
+// fails with TimedOut if no call arrives within the ticks
+pub fn receive_timeout(ticks: u32) -> Result<(u32, u32), SysError> {
+    let result: u32;
+    let client: u32;
+    let request: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            lateout("r1") client,
+            lateout("r2") request,
+            in("r0") RECEIVE_CALL_TIMEOUT,
+            in("r1") ticks,
+        );
+    }
+    into_result(result).map(|_| (client, request))
+}
+
+pub fn reply(client: u32, value: u32) -> Result<(), SysError> {
+    let result: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            in("r0") REPLY,
+            in("r1") client,
+            in("r2") value,
+        );
+    }
+    into_result(result)
+}
+
+pub fn register_name(name: &str) -> Result<(), SysError> {
+    let result: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            in("r0") REGISTER_NAME,
+            in("r1") name.as_ptr(),
+            in("r2") name.len(),
+        );
+    }
+    into_result(result)
+}
+
+// blocks until the name is registered
+pub fn lookup(name: &str) -> Result<u32, SysError> {
+    let result: u32;
+    let id: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            lateout("r1") id,
+            in("r0") LOOKUP,
+            in("r1") name.as_ptr(),
+            in("r2") name.len(),
+        );
+    }
+    into_result(result).map(|_| id)
+}
+
+// fails with TimedOut if the name is not registered within the ticks
+pub fn lookup_timeout(name: &str, ticks: u32) -> Result<u32, SysError> {
+    let result: u32;
+    let id: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            lateout("r1") id,
+            in("r0") LOOKUP_TIMEOUT,
+            in("r1") name.as_ptr(),
+            in("r2") name.len(),
+            in("r3") ticks,
+        );
+    }
+    into_result(result).map(|_| id)
+}
+
+pub fn mutex_create() -> Result<u32, SysError> {
+    create_sync_object(MUTEX_CREATE, 0)
+}
+
+// blocks until the mutex is acquired
+pub fn mutex_lock(handle: u32) -> Result<(), SysError> {
+    sync_object_op(MUTEX_LOCK, handle)
+}
+
+// fails with TimedOut if the mutex is not acquired within the ticks
+pub fn mutex_lock_timeout(handle: u32, ticks: u32) -> Result<(), SysError> {
+    sync_object_op_timeout(MUTEX_LOCK_TIMEOUT, handle, ticks)
+}
+
+pub fn mutex_unlock(handle: u32) -> Result<(), SysError> {
+    sync_object_op(MUTEX_UNLOCK, handle)
+}
+
+pub fn semaphore_create(count: u32) -> Result<u32, SysError> {
+    create_sync_object(SEMAPHORE_CREATE, count)
+}
+
+// blocks until the count is positive
+pub fn semaphore_wait(handle: u32) -> Result<(), SysError> {
+    sync_object_op(SEMAPHORE_WAIT, handle)
+}
+
+// fails with TimedOut if the count is not positive within the ticks
+pub fn semaphore_wait_timeout(handle: u32, ticks: u32) -> Result<(), SysError> {
+    sync_object_op_timeout(SEMAPHORE_WAIT_TIMEOUT, handle, ticks)
+}
 
-    if result > 0 {
-        Some(message)
-    } else {
-        None
+pub fn semaphore_signal(handle: u32) -> Result<(), SysError> {
+    sync_object_op(SEMAPHORE_SIGNAL, handle)
+}
+
+fn create_sync_object(id: u32, arg: u32) -> Result<u32, SysError> {
+    let result: u32;
+    let handle: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            lateout("r1") handle,
+            in("r0") id,
+            in("r1") arg,
+        );
     }
+    into_result(result).map(|_| handle)
 }
 
-pub fn wait_for_interrupt(id: u32) {
+fn sync_object_op(id: u32, handle: u32) -> Result<(), SysError> {
+    let result: u32;
     unsafe {
         asm!(
             "svc 1",
+            lateout("r0") result,
+            in("r0") id,
+            in("r1") handle,
+        );
+    }
+    into_result(result)
+}
+
+fn sync_object_op_timeout(id: u32, handle: u32, ticks: u32) -> Result<(), SysError> {
+    let result: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            in("r0") id,
+            in("r1") handle,
+            in("r2") ticks,
+        );
+    }
+    into_result(result)
+}
+
+pub fn notify(id: u32, flags: u32) -> Result<(), SysError> {
+    let result: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            in("r0") NOTIFY,
+            in("r1") id,
+            in("r2") flags,
+        );
+    }
+    into_result(result)
+}
+
+// blocks until any or all of the flags in the mask are posted, and returns and clears them
+pub fn wait_flags(mask: u32, mode: WaitMode) -> Result<u32, SysError> {
+    let result: u32;
+    let flags: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            lateout("r1") flags,
+            in("r0") WAIT_FLAGS,
+            in("r1") mask,
+            in("r2") mode as u32,
+        );
+    }
+    into_result(result).map(|_| flags)
+}
+
+// fails with TimedOut if the flags are not posted within the ticks
+pub fn wait_flags_timeout(mask: u32, mode: WaitMode, ticks: u32) -> Result<u32, SysError> {
+    let result: u32;
+    let flags: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            lateout("r1") flags,
+            in("r0") WAIT_FLAGS_TIMEOUT,
+            in("r1") mask,
+            in("r2") mode as u32,
+            in("r3") ticks,
+        );
+    }
+    into_result(result).map(|_| flags)
+}
+
+// fails if no handler is registered for the IRQ
+pub fn wait_for_interrupt(id: u32) -> Result<(), SysError> {
+    let result: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
             in("r0") WAIT_IRQ,
             in("r1") id,
         );
     }
+    into_result(result)
+}
+
+// fails with TimedOut if the interrupt does not come within the ticks
+pub fn wait_for_interrupt_timeout(id: u32, ticks: u32) -> Result<(), SysError> {
+    let result: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            in("r0") WAIT_IRQ_TIMEOUT,
+            in("r1") id,
+            in("r2") ticks,
+        );
+    }
+    into_result(result)
 }
 
-pub fn wait_for_event() {
+pub fn wait_for_event() -> Result<(), SysError> {
+    let result: u32;
     unsafe {
         asm!(
             "svc 1",
+            lateout("r0") result,
             in("r0") YIELD,
         );
     }
+    into_result(result)
 }
 
-pub fn wait_for_systick() {
+pub fn wait_for_systick() -> Result<(), SysError> {
+    let result: u32;
     unsafe {
         asm!(
             "svc 1",
+            lateout("r0") result,
             in("r0") WAIT_SYSTICK,
         );
     }
+    into_result(result)
+}
+
+pub fn sleep(ticks: u32) -> Result<(), SysError> {
+    let result: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            in("r0") SLEEP,
+            in("r1") ticks,
+        );
+    }
+    into_result(result)
+}
+
+pub fn sleep_until(tick: u64) -> Result<(), SysError> {
+    let result: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            in("r0") SLEEP_UNTIL,
+            in("r1") tick as u32,
+            in("r2") (tick >> 32) as u32,
+        );
+    }
+    into_result(result)
 }
 
-pub fn print_str(message: &str) {
+pub fn exit(code: u32) -> ! {
+    unsafe {
+        asm!(
+            "svc 1",
+            in("r0") EXIT,
+            in("r1") code,
+            options(noreturn),
+        );
+    }
+}
+
+pub fn kill(id: u32) -> Result<(), SysError> {
+    let result: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            in("r0") KILL,
+            in("r1") id,
+        );
+    }
+    into_result(result)
+}
+
+// the exit code is only kept for the last few processes which exited before they were waited for
+pub fn wait(id: u32) -> Result<u32, SysError> {
+    let result: u32;
+    let code: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            lateout("r1") code,
+            in("r0") WAIT,
+            in("r1") id,
+        );
+    }
+    into_result(result).map(|_| code)
+}
+
+// fails with TimedOut if the process does not exit within the ticks
+pub fn wait_timeout(id: u32, ticks: u32) -> Result<u32, SysError> {
+    let result: u32;
+    let code: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            lateout("r1") code,
+            in("r0") WAIT_TIMEOUT,
+            in("r1") id,
+            in("r2") ticks,
+        );
+    }
+    into_result(result).map(|_| code)
+}
+
+pub fn spawn(
+    entry: extern "C" fn(u32) -> !,
+    stack_size: usize,
+    arg: u32,
+    priority: usize,
+) -> Result<u32, SysError> {
+    let result: u32;
+    let value: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            lateout("r1") value,
+            in("r0") SPAWN,
+            in("r1") entry as usize as u32,
+            in("r2") stack_size,
+            in("r3") arg,
+            in("r12") priority,
+        );
+    }
+    into_result(result).map(|_| value)
+}
+
+// fails if the message is not in the memory of the process
+pub fn print_str(message: &str) -> Result<(), SysError> {
     let message_ptr = message.as_ptr();
     let length = message.bytes().len();
+    let result: u32;
     unsafe {
         asm!(
             "svc 1",
-            in("r0") 1,
+            lateout("r0") result,
+            in("r0") PRINT,
             in("r1") message_ptr,
             in("r2") length,
         );
     }
+    into_result(result)
+}
+
+// kind is one of PROCESS_STATS, SYSCALL_STATS and IRQ_STATS
+pub fn stats(kind: u32, id: u32) -> Result<Stats, SysError> {
+    let result: u32;
+    let count: u32;
+    let low: u32;
+    let high: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            lateout("r1") count,
+            lateout("r2") low,
+            lateout("r3") high,
+            in("r0") STATS,
+            in("r1") kind,
+            in("r2") id,
+        );
+    }
+    into_result(result).map(|_| Stats {
+        count,
+        cycles: ((high as u64) << 32) | low as u64,
+    })
+}
+
+// ticks since the kernel started and the ticks per second
+pub fn now() -> Result<(u64, u32), SysError> {
+    let result: u32;
+    let low: u32;
+    let high: u32;
+    let tick_hz: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            lateout("r1") low,
+            lateout("r2") high,
+            lateout("r3") tick_hz,
+            in("r0") NOW,
+        );
+    }
+    into_result(result).map(|_| (((high as u64) << 32) | low as u64, tick_hz))
+}
+
+// posts the message to the target after the period in ticks, and again every period if periodic
+pub fn timer_create(
+    period: u32,
+    mode: TimerMode,
+    target: u32,
+    message: u32,
+) -> Result<u32, SysError> {
+    let result: u32;
+    let id: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            lateout("r1") id,
+            in("r0") TIMER_CREATE,
+            in("r1") period,
+            in("r2") mode as u32,
+            in("r3") target,
+            in("r12") message,
+        );
+    }
+    into_result(result).map(|_| id)
+}
+
+// only the process which created the timer can cancel it
+pub fn timer_cancel(id: u32) -> Result<(), SysError> {
+    let result: u32;
+    unsafe {
+        asm!(
+            "svc 1",
+            lateout("r0") result,
+            in("r0") TIMER_CANCEL,
+            in("r1") id,
+        );
+    }
+    into_result(result)
 }
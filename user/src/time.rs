@@ -0,0 +1,65 @@
+use crate::syscall::now;
+use core::ops::{Add, Sub};
+pub use core::time::Duration;
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+// a point on the monotonic clock of the kernel
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Instant {
+    ticks: u64,
+    tick_hz: u32,
+}
+
+impl Instant {
+    pub fn now() -> Instant {
+        let (ticks, tick_hz) = now().unwrap();
+        Instant { ticks, tick_hz }
+    }
+
+    // for sleep_until
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    // zero if earlier is later than self
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        ticks_to_duration(self.ticks.saturating_sub(earlier.ticks), self.tick_hz)
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    // rounded up to the next tick
+    fn add(self, duration: Duration) -> Instant {
+        Instant {
+            ticks: self.ticks + duration_to_ticks(duration, self.tick_hz),
+            tick_hz: self.tick_hz,
+        }
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+fn ticks_to_duration(ticks: u64, tick_hz: u32) -> Duration {
+    let tick_hz = tick_hz as u64;
+    let nanos = (ticks % tick_hz) * NANOS_PER_SEC / tick_hz;
+    Duration::new(ticks / tick_hz, nanos as u32)
+}
+
+fn duration_to_ticks(duration: Duration, tick_hz: u32) -> u64 {
+    let tick_hz = tick_hz as u64;
+    let nanos = duration.subsec_nanos() as u64 * tick_hz;
+    duration.as_secs() * tick_hz + (nanos + NANOS_PER_SEC - 1) / NANOS_PER_SEC
+}
@@ -1,9 +1,10 @@
 use core::ptr::NonNull;
 
-use alloc::alloc::{GlobalAlloc, Layout};
+use core::alloc::{GlobalAlloc, Layout};
 use crate::sync::UnsafeLock;
 
-const LIST_LEN: usize = 5;
+// number of size classes of an allocator created with `new`
+pub const LIST_LEN: usize = 5;
 
 struct FreeArea {
     next: Option<NonNull<u8>>,
@@ -26,17 +27,26 @@ impl FreeArea {
 unsafe impl Send for FreeArea {}
 
 
-struct Allocator {
-    free_list_head: [FreeArea; LIST_LEN],
+// the largest block of an allocator with N size classes is MIN_BASE_SIZE << (N - 1)
+struct Allocator<const N: usize = LIST_LEN> {
+    free_list_head: [FreeArea; N],
 }
 
 const MIN_BASE_SIZE: usize = core::mem::size_of::<FreeArea>();
-const MAX_BASE_SIZE: usize = MIN_BASE_SIZE << (LIST_LEN - 1);
 
 impl Allocator {
     pub const fn new() -> Self {
+        Self::with_size_classes()
+    }
+}
+
+impl<const N: usize> Allocator<N> {
+    const MAX_BASE_SIZE: usize = MIN_BASE_SIZE << (N - 1);
+
+    pub const fn with_size_classes() -> Self {
+        const EMPTY: FreeArea = FreeArea::new();
         Self {
-            free_list_head: [FreeArea::new(), FreeArea::new(), FreeArea::new(), FreeArea::new(), FreeArea::new()],
+            free_list_head: [EMPTY; N],
         }
     }
 
@@ -83,7 +93,7 @@ impl Allocator {
         new_area.next = next;
 
         // join consecutive areas
-        if idx < LIST_LEN - 1 {
+        if idx < N - 1 {
             self.optimize_area(idx);
         }
     }
@@ -95,7 +105,7 @@ impl Allocator {
 
 
         // align to max base size
-        while current + (MIN_BASE_SIZE << idx) <= heap_end && idx < LIST_LEN - 1 {
+        while current + (MIN_BASE_SIZE << idx) <= heap_end && idx < N - 1 {
             let base_size = MIN_BASE_SIZE << idx;
             let next_base_size = base_size * 2;
             if current % next_base_size > 0 {
@@ -104,9 +114,9 @@ impl Allocator {
             }
             idx += 1;
         }
-        while current + MAX_BASE_SIZE <= heap_end && idx < LIST_LEN {
+        while current + Self::MAX_BASE_SIZE <= heap_end && idx < N {
             self.push_area_to_list(idx, current);
-            current += MAX_BASE_SIZE;
+            current += Self::MAX_BASE_SIZE;
         }
         // allocate rest of area
         idx += 1;
@@ -120,23 +130,30 @@ impl Allocator {
         }
     }
 
-    fn alloc(&mut self, layout: Layout) -> *mut u8 {
-        let size = layout.size();
-        let align = layout.align();
+    // the smallest size class which holds the layout, its blocks are at least as large as the alignment
+    fn size_class(layout: &Layout) -> usize {
+        let size = layout.size().max(layout.align());
         let mut idx = 0;
-        while (MIN_BASE_SIZE << idx) < size && idx < LIST_LEN {
+        while (MIN_BASE_SIZE << idx) < size && idx < N {
             idx += 1;
         }
-        
+        idx
+    }
+
+    fn alloc(&mut self, layout: Layout) -> *mut u8 {
+        let align = layout.align();
+        let mut idx = Self::size_class(&layout);
         let min_idx = idx;
-        while idx < LIST_LEN {
+        while idx < N {
             let target_list = &mut self.free_list_head[idx];
             let mut flag = false;
             let mut prev = target_list as *mut _  as *mut u8;
             let mut next = target_list.next;
             while next.is_some() {
                 let next_ptr = next.as_ref().unwrap().as_ptr();
-                if (next_ptr as usize) % align + size <= (MIN_BASE_SIZE << idx) {
+                // the free block is not aligned to its size after joining, so check the aligned block fits
+                let tar_addr = (next_ptr as usize + align - 1) / align * align;
+                if tar_addr + (MIN_BASE_SIZE << min_idx) <= next_ptr as usize + (MIN_BASE_SIZE << idx) {
                     flag = true;
                     break;
                 }
@@ -158,7 +175,7 @@ impl Allocator {
                 if next_end > tar_end {
                     self.add_area(tar_end, next_end);
                 }
-                return next_ptr;
+                return tar_addr as *mut u8;
             }
 
 
@@ -170,24 +187,30 @@ impl Allocator {
 
     fn dealloc(&mut self, ptr: *mut u8, layout: Layout) {
         let heap_start = ptr as usize;
-        let heap_end = heap_start + (layout.size() + MIN_BASE_SIZE - 1) / MIN_BASE_SIZE * MIN_BASE_SIZE;
+        let heap_end = heap_start + (MIN_BASE_SIZE << Self::size_class(&layout));
         self.add_area(heap_start, heap_end);
     }
 }
 
-pub struct LockedAllocator(UnsafeLock<Allocator>);
+pub struct LockedAllocator<const N: usize = LIST_LEN>(UnsafeLock<Allocator<N>>);
 
 impl LockedAllocator {
     pub const fn new() -> Self {
         Self(UnsafeLock::new(Allocator::new()))
     }
+}
+
+impl<const N: usize> LockedAllocator<N> {
+    pub const fn with_size_classes() -> Self {
+        Self(UnsafeLock::new(Allocator::with_size_classes()))
+    }
 
     pub fn add_area(&self, heap_start: usize, heap_end: usize) {
         self.0.lock().add_area(heap_start, heap_end);
     }
 }
 
-unsafe impl GlobalAlloc for LockedAllocator {
+unsafe impl<const N: usize> GlobalAlloc for LockedAllocator<N> {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
         let mut lock = self.0.lock();
         lock.alloc(layout)
@@ -202,7 +225,7 @@ unsafe impl GlobalAlloc for LockedAllocator {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use alloc::vec;
+    use std::vec;
     use array_init::array_init;
     use rand::{Rng, SeedableRng, StdRng};
     extern crate std;
@@ -250,6 +273,76 @@ mod tests {
         assert_eq!(1, *val5);
     }
     
+    #[test]
+    fn test_large() {
+        let buffer = [0u8; 8200];
+        let heap_start = &buffer[0] as *const u8 as usize;
+        let heap_end = heap_start + 8200;
+        let mut allocator = Allocator::<12>::with_size_classes();
+
+        allocator.add_area(heap_start, heap_end);
+        let layout = Layout::from_size_align(1024, 8).unwrap();
+        let mem1 = allocator.alloc(layout);
+        let mem2 = allocator.alloc(layout);
+        assert!(!mem1.is_null());
+        assert!(!mem2.is_null());
+        assert!((mem1 as usize + 1024 <= mem2 as usize) || (mem2 as usize + 1024 <= mem1 as usize));
+        assert!(allocator.alloc(Layout::from_size_align(Allocator::<12>::MAX_BASE_SIZE * 2, 8).unwrap()).is_null());
+
+        allocator.dealloc(mem1, layout);
+        allocator.dealloc(mem2, layout);
+        let mem3 = allocator.alloc(Layout::from_size_align(4096, 8).unwrap());
+        assert!(!mem3.is_null());
+        assert!(mem3 as usize >= heap_start && mem3 as usize + 4096 <= heap_end);
+    }
+
+    #[test]
+    fn test_size_aligned() {
+        let buffer = [0u8; 8200];
+        let heap_start = &buffer[0] as *const u8 as usize;
+        let heap_end = heap_start + 8200;
+        let mut allocator = Allocator::<12>::with_size_classes();
+
+        allocator.add_area(heap_start, heap_end);
+        let small = allocator.alloc(Layout::from_size_align(64, 8).unwrap());
+        assert!(!small.is_null());
+        let layout = Layout::from_size_align(1024, 1024).unwrap();
+        let mem1 = allocator.alloc(layout);
+        let mem2 = allocator.alloc(layout);
+        assert_eq!(0, mem1 as usize % 1024);
+        assert_eq!(0, mem2 as usize % 1024);
+        assert_ne!(mem1, mem2);
+        assert!(mem1 as usize + 1024 <= heap_end && mem2 as usize + 1024 <= heap_end);
+    }
+
+    #[test]
+    fn test_align_larger_than_size() {
+        let buffer = [0u8; 1024];
+        let heap_start = &buffer[0] as *const u8 as usize;
+        let heap_end = heap_start + 1024;
+        let mut allocator = Allocator::new();
+
+        allocator.add_area(heap_start, heap_end);
+        let small = allocator.alloc(Layout::from_size_align(8, 8).unwrap());
+        assert!(!small.is_null());
+        let layout = Layout::from_size_align(16, 64).unwrap();
+        let mem1 = allocator.alloc(layout);
+        let mem2 = allocator.alloc(layout);
+        assert!(!mem1.is_null());
+        assert!(!mem2.is_null());
+        assert_eq!(0, mem1 as usize % 64);
+        assert_eq!(0, mem2 as usize % 64);
+        assert!((mem1 as usize + 64 <= mem2 as usize) || (mem2 as usize + 64 <= mem1 as usize));
+        assert!(mem1 as usize >= small as usize + 8 || mem1 as usize + 64 <= small as usize);
+        assert!(mem1 as usize + 64 <= heap_end && mem2 as usize + 64 <= heap_end);
+        assert!(allocator.alloc(Layout::from_size_align(8, 256).unwrap()).is_null());
+
+        allocator.dealloc(mem1, layout);
+        allocator.dealloc(mem2, layout);
+        let mem3 = allocator.alloc(layout);
+        assert_eq!(0, mem3 as usize % 64);
+    }
+
     #[test]
     fn test_rand() {
         let seed: &[_] = &[1, 2, 3, 4];
@@ -1,6 +1,5 @@
 use crate::binary_tree::BinaryTree;
 use core::cmp;
-use core::mem;
 use core::ops::{Deref, DerefMut};
 use core::ptr::NonNull;
 
@@ -32,10 +31,6 @@ impl<'a, K, V> Node<'a, K, V> {
         self.parent.as_ref().map(|ptr| unsafe { ptr.as_ref() })
     }
 
-    fn get_parent_mut(&mut self) -> Option<&mut Node<'a, K, V>> {
-        self.parent.as_mut().map(|ptr| unsafe { ptr.as_mut() })
-    }
-
     fn get_min_child(&self) -> &Node<'a, K, V> {
         let mut current = self;
         while current.left.is_some() {
@@ -57,62 +52,58 @@ impl<'a, K, V> Node<'a, K, V> {
             self.left.as_ref().map(|n| n.height).unwrap_or(0),
             self.right.as_ref().map(|n| n.height).unwrap_or(0),
         );
-        self.get_parent_mut().map(|p| p.update_height());
     }
 
-    fn rotate_right(&mut self) -> bool {
-        if self.left.is_none() {
-            return false;
-        }
-
-        let mut left_node = self.left.take();
-        let left_node_ref = left_node.as_mut().unwrap();
+    fn balance_factor(&self) -> isize {
+        self.left.as_ref().map(|n| n.height).unwrap_or(0) as isize
+            - self.right.as_ref().map(|n| n.height).unwrap_or(0) as isize
+    }
 
-        mem::swap(self, left_node_ref);
+    fn reset(&mut self) {
         self.parent = None;
-        let self_ptr = unsafe { NonNull::new_unchecked(self as *mut Node<'a, K, V>) };
-        left_node_ref.parent = Some(self_ptr);
-        let left_right_node = self.right.take();
-        left_node_ref.left = left_right_node;
-        self.left.as_mut().map(|node| {
-            node.parent = Some(self_ptr);
-        });
-        let left_node_ptr =
-            unsafe { NonNull::new_unchecked(*left_node_ref as *mut Node<'a, K, V>) };
-        left_node_ref.right.as_mut().map(|node| {
-            node.parent = Some(left_node_ptr);
-        });
-        self.right = left_node;
-        self.right.as_mut().map(|node| node.update_height());
-        true
+        self.left = None;
+        self.right = None;
+        self.height = 1;
     }
+}
 
-    fn rotate_left(&mut self) -> bool {
-        if self.right.is_none() {
-            return false;
-        }
+// rotate the subtree owned by `slot` and return the new root of the subtree
+fn rotate_right<'a, K, V>(slot: &mut Option<&'a mut Node<'a, K, V>>) -> NonNull<Node<'a, K, V>> {
+    let node = slot.take().unwrap();
+    let left = node.left.take().unwrap();
+    let node_ptr = unsafe { NonNull::new_unchecked(node as *mut Node<'a, K, V>) };
+    let left_ptr = unsafe { NonNull::new_unchecked(left as *mut Node<'a, K, V>) };
 
-        let mut right_node = self.right.take();
-        let right_node_ref = right_node.as_mut().unwrap();
+    node.left = left.right.take();
+    if let Some(n) = node.left.as_mut() {
+        n.parent = Some(node_ptr);
+    }
+    left.parent = node.parent;
+    node.parent = Some(left_ptr);
+    node.update_height();
+    left.right = Some(node);
+    left.update_height();
+    slot.replace(left);
+    left_ptr
+}
 
-        mem::swap(self, right_node_ref);
-        self.parent = None;
-        let self_ptr = unsafe { NonNull::new_unchecked(self as *mut Node<'a, K, V>) };
-        right_node_ref.parent = Some(self_ptr);
-        let right_left_node = self.left.take();
-        right_node_ref.right = right_left_node;
-        self.right.as_mut().map(|node| {
-            node.parent = Some(self_ptr);
-        });
-        let right_node_ptr =
-            unsafe { NonNull::new_unchecked(*right_node_ref as *mut Node<'a, K, V>) };
-        right_node_ref.left.as_mut().map(|node| {
-            node.parent = Some(right_node_ptr);
-        });
-        self.left = right_node;
-        self.left.as_mut().map(|node| node.update_height());
-        true
+fn rotate_left<'a, K, V>(slot: &mut Option<&'a mut Node<'a, K, V>>) -> NonNull<Node<'a, K, V>> {
+    let node = slot.take().unwrap();
+    let right = node.right.take().unwrap();
+    let node_ptr = unsafe { NonNull::new_unchecked(node as *mut Node<'a, K, V>) };
+    let right_ptr = unsafe { NonNull::new_unchecked(right as *mut Node<'a, K, V>) };
+
+    node.right = right.left.take();
+    if let Some(n) = node.right.as_mut() {
+        n.parent = Some(node_ptr);
     }
+    right.parent = node.parent;
+    node.parent = Some(right_ptr);
+    node.update_height();
+    right.left = Some(node);
+    right.update_height();
+    slot.replace(right);
+    right_ptr
 }
 
 pub struct AvlTree<'a, K, V> {
@@ -130,14 +121,6 @@ pub struct IterMut<'a, K, V> {
     size: usize,
 }
 
-#[derive(Debug, PartialEq)]
-enum BalanceResult {
-    Balanced,
-    LeftHeavy,
-    RightHeavy,
-}
-
-// TODO: implement rotate
 impl<'a, K, V> AvlTree<'a, K, V>
 where
     K: Ord,
@@ -179,47 +162,73 @@ where
         }
     }
 
-    fn check_balanced(&self) -> BalanceResult {
-        if self.head.is_none() {
-            return BalanceResult::Balanced;
-        }
-        let head = self.head.as_ref().unwrap();
-        let left_height = head.left.as_ref().map(|n| n.height).unwrap_or(0);
-        let right_height = head.right.as_ref().map(|n| n.height).unwrap_or(0);
-        if left_height >= right_height + 2 {
-            BalanceResult::LeftHeavy
-        } else if left_height + 2 <= right_height {
-            BalanceResult::RightHeavy
-        } else {
-            BalanceResult::Balanced
+    // the slot of the parent (or the head) which owns the node
+    unsafe fn get_slot(
+        &mut self,
+        node: NonNull<Node<'a, K, V>>,
+    ) -> &mut Option<&'a mut Node<'a, K, V>> {
+        match node.as_ref().parent {
+            None => &mut self.head,
+            Some(parent) => {
+                let parent = &mut *parent.as_ptr();
+                let is_left = parent
+                    .left
+                    .as_ref()
+                    .map(|left| left.is_same(node.as_ref()))
+                    .unwrap_or(false);
+                if is_left {
+                    &mut parent.left
+                } else {
+                    &mut parent.right
+                }
+            }
         }
     }
 
-    fn balance(&mut self) {
-        match self.check_balanced() {
-            BalanceResult::LeftHeavy => {
-                let head = self.head.as_mut().unwrap();
-                let left = head.left.as_mut().unwrap();
-                let llh = left.left.as_ref().map(|n| n.height).unwrap_or(0);
-                let lrh = left.right.as_ref().map(|n| n.height).unwrap_or(0);
-                if llh < lrh {
-                    left.rotate_left();
+    // update heights and rotate from the given node up to the head
+    fn balance(&mut self, start: Option<NonNull<Node<'a, K, V>>>) {
+        let mut current = start;
+        while let Some(mut ptr) = current {
+            let node = unsafe { ptr.as_mut() };
+            node.update_height();
+            let balance_factor = node.balance_factor();
+            let mut subtree_ptr = ptr;
+            if balance_factor > 1 {
+                if node.left.as_ref().unwrap().balance_factor() < 0 {
+                    rotate_left(&mut node.left);
                 }
-                head.rotate_right();
-            }
-            BalanceResult::RightHeavy => {
-                let head = self.head.as_mut().unwrap();
-                let right = head.right.as_mut().unwrap();
-                let rlh = right.left.as_ref().map(|n| n.height).unwrap_or(0);
-                let rrh = right.right.as_ref().map(|n| n.height).unwrap_or(0);
-                if rrh < rlh {
-                    right.rotate_right();
+                subtree_ptr = rotate_right(unsafe { self.get_slot(ptr) });
+            } else if balance_factor < -1 {
+                if node.right.as_ref().unwrap().balance_factor() > 0 {
+                    rotate_right(&mut node.right);
                 }
-                head.rotate_left();
+                subtree_ptr = rotate_left(unsafe { self.get_slot(ptr) });
             }
-            _ => {}
+            current = unsafe { subtree_ptr.as_ref().parent };
         }
     }
+
+    fn find(&mut self, key: &K) -> Option<NonNull<Node<'a, K, V>>> {
+        let mut current = self
+            .head
+            .as_mut()
+            .map(|head| unsafe { NonNull::new_unchecked(*head as *mut Node<'a, K, V>) });
+        while let Some(mut ptr) = current {
+            let node = unsafe { ptr.as_mut() };
+            if node.item.0 == *key {
+                break;
+            }
+            let next = if node.item.0 < *key {
+                &mut node.right
+            } else {
+                &mut node.left
+            };
+            current = next
+                .as_mut()
+                .map(|n| unsafe { NonNull::new_unchecked(*n as *mut Node<'a, K, V>) });
+        }
+        current
+    }
 }
 
 impl<'a, K, V> BinaryTree<'a, K, V, Node<'a, K, V>> for AvlTree<'a, K, V>
@@ -323,6 +332,7 @@ where
     }
 
     fn insert(&mut self, node: &'a mut Node<'a, K, V>) {
+        node.reset();
         if self.head.is_none() {
             self.head.replace(node);
         } else {
@@ -333,7 +343,7 @@ where
                         let np = unsafe { NonNull::new_unchecked(*current as *mut Node<'a, K, V>) };
                         node.parent.replace(np);
                         current.right.replace(node);
-                        current.update_height();
+                        self.balance(Some(np));
                         break;
                     } else {
                         current = current.right.as_mut().unwrap();
@@ -343,7 +353,7 @@ where
                         let np = unsafe { NonNull::new_unchecked(*current as *mut Node<'a, K, V>) };
                         node.parent.replace(np);
                         current.left.replace(node);
-                        current.update_height();
+                        self.balance(Some(np));
                         break;
                     } else {
                         current = current.left.as_mut().unwrap();
@@ -351,9 +361,60 @@ where
                 }
             }
         }
-        self.balance();
         self.size += 1;
     }
+
+    fn remove(&mut self, key: &K) -> Option<&'a mut Node<'a, K, V>> {
+        let mut target_ptr = self.find(key)?;
+        let target = unsafe { target_ptr.as_mut() };
+        let parent = target.parent;
+        let balance_start;
+        let replacement = if target.left.is_some() && target.right.is_some() {
+            // replace the target with the minimum node of the right subtree
+            let right = target.right.as_mut().unwrap();
+            let mut min_ptr =
+                unsafe { NonNull::new_unchecked(right.get_min_child_mut() as *mut Node<'a, K, V>) };
+            let min_parent = unsafe { min_ptr.as_ref().parent };
+            let min_node = unsafe { self.get_slot(min_ptr) }.take().unwrap();
+            let min_node_ptr = unsafe { min_ptr.as_mut() } as *mut Node<'a, K, V>;
+            if min_parent == Some(target_ptr) {
+                balance_start = Some(min_ptr);
+            } else {
+                let mut min_parent = min_parent.unwrap();
+                let min_parent_ref = unsafe { min_parent.as_mut() };
+                min_parent_ref.left = min_node.right.take();
+                if let Some(n) = min_parent_ref.left.as_mut() {
+                    n.parent = Some(min_parent);
+                }
+                min_node.right = target.right.take();
+                balance_start = Some(min_parent);
+            }
+            let min_node_nn = unsafe { NonNull::new_unchecked(min_node_ptr) };
+            min_node.left = target.left.take();
+            if let Some(n) = min_node.left.as_mut() {
+                n.parent = Some(min_node_nn);
+            }
+            if let Some(n) = min_node.right.as_mut() {
+                n.parent = Some(min_node_nn);
+            }
+            Some(min_node)
+        } else {
+            balance_start = parent;
+            target.left.take().or_else(|| target.right.take())
+        };
+
+        let slot = unsafe { self.get_slot(target_ptr) };
+        let removed = slot.take().unwrap();
+        *slot = replacement;
+        if let Some(n) = slot.as_mut() {
+            n.parent = parent;
+        }
+        removed.reset();
+
+        self.balance(balance_start);
+        self.size -= 1;
+        Some(removed)
+    }
 }
 
 impl<'a, K, V> Deref for Node<'a, K, V> {
@@ -462,6 +523,34 @@ mod tests {
     use array_init::array_init;
     use rand::{Rng, SeedableRng, StdRng};
 
+    // returns the height of the subtree if every node in it is balanced
+    fn check_node<K: Ord, V>(node: &Node<K, V>) -> Option<usize> {
+        let mut heights = [0, 0];
+        for (i, child) in [&node.left, &node.right].iter().enumerate() {
+            if let Some(child) = child {
+                if child.parent.map(|p| p.as_ptr() as *const _) != Some(node as *const _) {
+                    return None;
+                }
+                heights[i] = check_node(child)?;
+            }
+        }
+        let height = 1 + cmp::max(heights[0], heights[1]);
+        if node.height != height
+            || cmp::max(heights[0], heights[1]) - cmp::min(heights[0], heights[1]) > 1
+        {
+            return None;
+        }
+        Some(height)
+    }
+
+    fn check_balanced<K: Ord, V>(btree: &AvlTree<K, V>) -> bool {
+        btree
+            .head
+            .as_ref()
+            .map(|head| head.parent.is_none() && check_node(head).is_some())
+            .unwrap_or(true)
+    }
+
     #[test]
     fn test_simple() {
         let mut btree = AvlTree::new();
@@ -524,12 +613,7 @@ mod tests {
         for node in nodes.iter_mut() {
             let i = node.item.0;
             btree.insert(node);
-            assert_eq!(
-                btree.check_balanced(),
-                BalanceResult::Balanced,
-                "not balanced when inserting {}",
-                i
-            );
+            assert!(check_balanced(&btree), "not balanced when inserting {}", i);
         }
         for i in 0..100 {
             assert_eq!(i, *(btree.get(&i).unwrap()));
@@ -539,4 +623,97 @@ mod tests {
             assert_eq!(&i, value);
         }
     }
+
+    #[test]
+    fn test_sorted_insert() {
+        let mut ascending: [Node<usize, usize>; 64] = array_init(|i| Node::new(i, i));
+        let mut btree = AvlTree::new();
+        for node in ascending.iter_mut() {
+            btree.insert(node);
+            assert!(check_balanced(&btree));
+        }
+        // a complete tree of 64 nodes has 7 levels
+        assert_eq!(7, btree.head.as_ref().unwrap().height);
+
+        let mut descending: [Node<usize, usize>; 64] = array_init(|i| Node::new(63 - i, i));
+        let mut btree = AvlTree::new();
+        for node in descending.iter_mut() {
+            btree.insert(node);
+            assert!(check_balanced(&btree));
+        }
+        assert_eq!(7, btree.head.as_ref().unwrap().height);
+        for (i, (key, value)) in btree.iter().enumerate() {
+            assert_eq!(&i, key);
+            assert_eq!(&(63 - i), value);
+        }
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut btree = AvlTree::new();
+        let mut node1 = Node::new(2, "second");
+        let mut node2 = Node::new(1, "first");
+        let mut node3 = Node::new(3, "third");
+        btree.insert(&mut node1);
+        btree.insert(&mut node2);
+        btree.insert(&mut node3);
+        assert!(btree.remove(&4).is_none());
+
+        let removed = btree.remove(&2).unwrap();
+        assert_eq!((2, "second"), removed.item);
+        assert!(btree.get(&2).is_none());
+        assert_eq!(&"first", btree.get(&1).unwrap());
+        assert_eq!(&"third", btree.get(&3).unwrap());
+        assert!(check_balanced(&btree));
+
+        // the removed node can be inserted again
+        removed.item.1 = "again";
+        btree.insert(removed);
+        assert_eq!(&"again", btree.get(&2).unwrap());
+
+        let expected = [1, 2, 3];
+        assert_eq!(3, btree.iter().count());
+        for (i, (key, _)) in btree.iter().enumerate() {
+            assert_eq!(&expected[i], key);
+        }
+        for key in expected.iter() {
+            assert_eq!(*key, btree.remove(key).unwrap().item.0);
+        }
+        assert_eq!(0, btree.iter().count());
+        assert!(btree.get(&1).is_none());
+    }
+
+    #[test]
+    fn test_random_remove() {
+        let seed: &[_] = &[4, 3, 2, 1];
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+        let mut nodes: [Node<usize, usize>; 100] = array_init(|i| Node::new(i, i));
+        let mut keys: [usize; 100] = array_init(|i| i);
+        rng.shuffle(&mut nodes);
+        rng.shuffle(&mut keys);
+        let mut btree = AvlTree::new();
+
+        for node in nodes.iter_mut() {
+            btree.insert(node);
+        }
+        for (count, key) in keys[0..50].iter().enumerate() {
+            let node = btree.remove(key).unwrap();
+            assert_eq!(*key, node.item.0);
+            assert_eq!(*key, node.item.1);
+            assert!(check_balanced(&btree), "not balanced when removing {}", key);
+            assert_eq!(99 - count, btree.iter().count());
+        }
+        for key in keys[0..50].iter() {
+            assert!(btree.get(key).is_none());
+        }
+        for key in keys[50..100].iter() {
+            assert_eq!(key, btree.get(key).unwrap());
+        }
+        let mut remaining: [usize; 50] = array_init(|i| keys[50 + i]);
+        remaining.sort();
+        for (i, (key, value)) in btree.iter().enumerate() {
+            assert_eq!(&remaining[i], key);
+            assert_eq!(&remaining[i], value);
+        }
+    }
 }
@@ -7,4 +7,5 @@ where
     fn get_mut(&mut self, key: &K) -> Option<&mut V>;
     fn borrow_mut(&mut self, key: &K) -> Option<&'a mut V>;
     fn insert(&mut self, node: &'a mut N);
+    fn remove(&mut self, key: &K) -> Option<&'a mut N>;
 }
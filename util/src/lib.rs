@@ -1,17 +1,14 @@
 #![no_std]
 #![crate_type = "rlib"]
 #![feature(const_maybe_uninit_assume_init)]
+#![feature(const_generics_defaults)]
 
+pub mod allocator;
 pub mod avl_tree;
 pub mod binary_tree;
 pub mod linked_list;
-#[cfg(feature = "alloc")]
-pub mod allocator;
 pub mod sync;
 
-#[cfg(feature = "alloc")]
-extern crate alloc;
-
 // copied from https://github.com/tock/tock
 macro_rules! static_init {
     ($T:ty, $e:expr) => {
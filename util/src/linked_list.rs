@@ -64,7 +64,7 @@ impl<'a, T> LinkedList<'a, T> {
                 result
             }
             None => {
-                panic!("empty list");
+                return None;
             }
         };
         let result = self.head.take();
@@ -88,6 +88,7 @@ impl<'a, T> LinkedList<'a, T> {
             let item_ptr = unsafe { NonNull::new_unchecked(item as *mut ListItem<T>) };
             self.last.replace(item_ptr);
             item.prev.replace(last_ptr);
+            item.next = None;
             unsafe {
                 last_ptr.as_mut().next.replace(item);
             }
@@ -95,6 +96,88 @@ impl<'a, T> LinkedList<'a, T> {
         self.len += 1;
     }
 
+    // insert the item before the first element which satisfies `f(item, element)`
+    pub fn insert_by<F>(&mut self, item: &'a mut ListItem<'a, T>, mut f: F)
+    where
+        F: FnMut(&T, &T) -> bool,
+    {
+        let item_ptr = unsafe { NonNull::new_unchecked(item as *mut ListItem<T>) };
+        let mut current = self
+            .head
+            .as_mut()
+            .map(|head| unsafe { NonNull::new_unchecked(*head as *mut ListItem<T>) });
+        while let Some(mut current_ptr) = current {
+            let node = unsafe { current_ptr.as_mut() };
+            if f(&item.item, &node.item) {
+                let prev = node.prev.replace(item_ptr);
+                item.prev = prev;
+                match prev {
+                    None => {
+                        item.next = self.head.take();
+                        self.head.replace(item);
+                    }
+                    Some(mut prev_ptr) => unsafe {
+                        item.next = prev_ptr.as_mut().next.take();
+                        prev_ptr.as_mut().next.replace(item);
+                    },
+                }
+                self.len += 1;
+                return;
+            }
+            current = node
+                .next
+                .as_mut()
+                .map(|next| unsafe { NonNull::new_unchecked(*next as *mut ListItem<T>) });
+        }
+        self.push(item);
+    }
+
+    // remove the first element which satisfies `f(element)`
+    pub fn remove_by<F>(&mut self, mut f: F) -> Option<&'a mut ListItem<'a, T>>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let mut current = self
+            .head
+            .as_mut()
+            .map(|head| unsafe { NonNull::new_unchecked(*head as *mut ListItem<T>) });
+        while let Some(mut current_ptr) = current {
+            let node = unsafe { current_ptr.as_mut() };
+            let next_ptr = node
+                .next
+                .as_mut()
+                .map(|next| unsafe { NonNull::new_unchecked(*next as *mut ListItem<T>) });
+            if f(&node.item) {
+                let next = node.next.take();
+                let prev = node.prev.take();
+                let result = match prev {
+                    None => {
+                        let result = self.head.take();
+                        self.head = next;
+                        result
+                    }
+                    Some(mut prev_ptr) => unsafe {
+                        let result = prev_ptr.as_mut().next.take();
+                        prev_ptr.as_mut().next = next;
+                        result
+                    },
+                };
+                match next_ptr {
+                    Some(mut next_ptr) => unsafe {
+                        next_ptr.as_mut().prev = prev;
+                    },
+                    None => {
+                        self.last = prev;
+                    }
+                }
+                self.len -= 1;
+                return result;
+            }
+            current = next_ptr;
+        }
+        None
+    }
+
     pub fn head_mut(&mut self) -> Option<&mut &'a mut ListItem<'a, T>> {
         self.head.as_mut()
     }
@@ -207,6 +290,7 @@ mod tests {
         let head: &u32 = list.pop().unwrap();
         assert_eq!(1, *head);
         assert!(list.is_empty());
+        assert!(list.pop().is_none());
     }
 
     #[test]
@@ -244,6 +328,60 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_insert_by() {
+        let mut item1 = ListItem::create(5);
+        let mut item2 = ListItem::create(1);
+        let mut item3 = ListItem::create(9);
+        let mut item4 = ListItem::create(3);
+        let mut item5 = ListItem::create(5);
+        let mut list = LinkedList::new();
+        list.insert_by(&mut item1, |item, element| item < element);
+        list.insert_by(&mut item2, |item, element| item < element);
+        list.insert_by(&mut item3, |item, element| item < element);
+        list.insert_by(&mut item4, |item, element| item < element);
+        list.insert_by(&mut item5, |item, element| item < element);
+        let expected = [1, 3, 5, 5, 9];
+        assert_eq!(expected.len(), list.iter().count());
+        for (i, item) in list.iter().enumerate() {
+            assert_eq!(expected[i], *item);
+        }
+        for i in 0..expected.len() {
+            let item: &u32 = list.pop().unwrap();
+            assert_eq!(expected[i], *item);
+        }
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn test_remove_by() {
+        let mut item1 = ListItem::create(1);
+        let mut item2 = ListItem::create(2);
+        let mut item3 = ListItem::create(3);
+        let mut item4 = ListItem::create(4);
+        let mut list = LinkedList::new();
+        list.push(&mut item1);
+        list.push(&mut item2);
+        list.push(&mut item3);
+        list.push(&mut item4);
+        assert!(list.remove_by(|item| *item == 5).is_none());
+        assert_eq!(3, **list.remove_by(|item| *item == 3).unwrap());
+        assert_eq!(1, **list.remove_by(|item| *item == 1).unwrap());
+        assert_eq!(4, **list.remove_by(|item| *item == 4).unwrap());
+        let expected = [2];
+        assert_eq!(expected.len(), list.iter().count());
+        let mut item5 = ListItem::create(5);
+        list.push(&mut item5);
+        let expected = [2, 5];
+        for (i, item) in list.iter().enumerate() {
+            assert_eq!(expected[i], *item);
+        }
+        assert_eq!(2, **list.remove_by(|item| *item == 2).unwrap());
+        assert_eq!(5, **list.remove_by(|item| *item == 5).unwrap());
+        assert!(list.is_empty());
+        assert_eq!(0, list.iter().count());
+    }
+
     #[test]
     fn test_join() {
         let mut list1 = LinkedList::new();